@@ -0,0 +1,120 @@
+/*
+ Copyright 2022 The Iteritor Authors
+
+ Licensed under the Apache License, Version 2.0 (the "License");
+ you may not use this file except in compliance with the License.
+ You may obtain a copy of the License at
+
+      https://www.apache.org/licenses/LICENSE-2.0
+
+ Unless required by applicable law or agreed to in writing, software
+ distributed under the License is distributed on an "AS IS" BASIS,
+ WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ See the License for the specific language governing permissions and
+ limitations under the License.
+ */
+
+//! Rough timings for the per-item hot paths. Run with `cargo bench`.
+//!
+//! This doesn't use a benchmarking framework, to avoid pulling one into the
+//! dev-dependencies; it just reports the best of a few timed runs.
+
+use std::{convert::Infallible, time::Instant};
+
+use iteritor::{buffer::UnsafeBuffer, IterFiltered, IterFolding};
+
+const ITEMS: u64 = 10_000_000;
+const RUNS: usize = 10;
+
+/// Stand-in for `std::hint::black_box`, which is newer than our MSRV.
+fn black_box<T>(value: T) -> T {
+    let value = core::mem::ManuallyDrop::new(value);
+    // SAFETY: `value` is never used or dropped again after this read.
+    unsafe { core::ptr::read_volatile(&*value) }
+}
+
+fn bench(name: &str, mut f: impl FnMut() -> u64) {
+    let best = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .min()
+        .unwrap();
+    println!(
+        "{:<32} {:>8.2} ns/item",
+        name,
+        best.as_nanos() as f64 / ITEMS as f64
+    );
+}
+
+fn all_ok() -> impl Iterator<Item = Result<u64, &'static str>> {
+    (0..ITEMS).map(|n| Ok(black_box(n)))
+}
+
+// a source whose `next` is slower than its `fold`.
+fn chained() -> impl Iterator<Item = Result<u64, &'static str>> {
+    (0..ITEMS / 2)
+        .chain(ITEMS / 2..ITEMS)
+        .map(|n| Ok(black_box(n)))
+}
+
+fn infallible() -> impl Iterator<Item = Result<u64, Infallible>> {
+    (0..ITEMS / 2)
+        .chain(ITEMS / 2..ITEMS)
+        .map(|n| Ok(black_box(n)))
+}
+
+fn some_err() -> impl Iterator<Item = Result<u64, &'static str>> {
+    (0..ITEMS).map(|n| {
+        if black_box(n) % 1000 == 999 {
+            Err("boom")
+        } else {
+            Ok(n)
+        }
+    })
+}
+
+fn main() {
+    bench("collect then sum", || {
+        all_ok()
+            .collect::<Result<Vec<_>, _>>()
+            .map_or(0, |v| v.into_iter().sum())
+    });
+    bench("with_folding sum", || {
+        all_ok().with_folding(|i| i.sum()).unwrap_or(0)
+    });
+    bench("with_folding sum, chained", || {
+        chained().with_folding(|i| i.sum()).unwrap_or(0)
+    });
+    bench("plain sum, infallible", || {
+        infallible().map(|n| n.unwrap_or_else(|e| match e {})).sum()
+    });
+    bench("with_folding sum, infallible", || {
+        infallible().with_folding(|i| i.sum()).unwrap_or(0)
+    });
+    bench("with_folding_infallible sum", || {
+        infallible()
+            .with_folding_infallible(|i| i.sum())
+            .unwrap_or(0)
+    });
+    bench("with_filtered, no errors", || {
+        all_ok()
+            .with_filtered(|i| i.map(|n| n * 2))
+            .filter_map(Result::ok)
+            .sum()
+    });
+    bench("with_filtered, errors", || {
+        some_err()
+            .with_filtered(|i| i.map(|n| n * 2))
+            .filter_map(Result::ok)
+            .sum()
+    });
+    bench("with_filtered, errors, unsafe", || {
+        some_err()
+            .with_filtered_buf(UnsafeBuffer::default(), |i| i.map(|n| n * 2))
+            .filter_map(Result::ok)
+            .sum()
+    });
+}
@@ -0,0 +1,168 @@
+/*
+ Copyright 2022 The Iteritor Authors
+
+ Licensed under the Apache License, Version 2.0 (the "License");
+ you may not use this file except in compliance with the License.
+ You may obtain a copy of the License at
+
+      https://www.apache.org/licenses/LICENSE-2.0
+
+ Unless required by applicable law or agreed to in writing, software
+ distributed under the License is distributed on an "AS IS" BASIS,
+ WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ See the License for the specific language governing permissions and
+ limitations under the License.
+ */
+
+//! Derive macros for the [iteritor](https://docs.rs/iteritor) crate. Use
+//! these through iteritor's `derive` feature rather than depending on this
+//! crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Ident, Type};
+
+/// Implements `iteritor::try_polyfill::Try` and `FromResidual` for an enum
+/// with one 'normal' variant and one divergent variant, each holding a single
+/// unnamed field. Mark them with `#[tryable(output)]` and
+/// `#[tryable(residual)]`:
+///
+/// ```
+/// # use iteritor::IterFolding;
+/// #[derive(Debug, PartialEq, iteritor::Tryable)]
+/// enum Outcome {
+///     #[tryable(output)]
+///     Done(u32),
+///     #[tryable(residual)]
+///     Failed(String),
+/// }
+///
+/// let total = [Outcome::Done(1), Outcome::Done(2)]
+///     .into_iter()
+///     .with_folding(|i| i.sum::<u32>());
+/// assert_eq!(total, Outcome::Done(3));
+/// ```
+///
+/// The enum can't have any other variants. The residual type is
+/// `Result<Infallible, E>` where `E` is the type held by the residual variant,
+/// the same as for `Result<T, E>`, so divergent values convert to and from
+/// `Result`s with the same error type.
+///
+/// With iteritor's `nightly` feature these are the real `core::ops` traits,
+/// so the crate using the derive needs `#![feature(try_trait_v2)]` as well.
+#[proc_macro_derive(Tryable, attributes(tryable))]
+pub fn derive_tryable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_tryable(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+enum Role {
+    Output,
+    Residual,
+}
+
+fn expand_tryable(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "Tryable can only be derived for enums",
+            ))
+        }
+    };
+
+    let mut output: Option<(&Ident, &Type)> = None;
+    let mut residual: Option<(&Ident, &Type)> = None;
+    for variant in &data.variants {
+        let mut role = None;
+        for attr in &variant.attrs {
+            if !attr.path().is_ident("tryable") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("output") {
+                    role = Some(Role::Output);
+                    Ok(())
+                } else if meta.path.is_ident("residual") {
+                    role = Some(Role::Residual);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `output` or `residual`"))
+                }
+            })?;
+        }
+
+        let role = role.ok_or_else(|| {
+            Error::new_spanned(
+                &variant.ident,
+                "every variant needs #[tryable(output)] or #[tryable(residual)]",
+            )
+        })?;
+        let ty = match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => &fields.unnamed[0].ty,
+            _ => {
+                return Err(Error::new_spanned(
+                    &variant.ident,
+                    "Tryable variants must have exactly one unnamed field",
+                ))
+            }
+        };
+        let slot = match role {
+            Role::Output => &mut output,
+            Role::Residual => &mut residual,
+        };
+        if slot.replace((&variant.ident, ty)).is_some() {
+            return Err(Error::new_spanned(
+                &variant.ident,
+                "only one variant can be marked as each of output and residual",
+            ));
+        }
+    }
+
+    let missing = |what| {
+        Error::new(
+            Span::call_site(),
+            format!("Tryable needs a variant marked #[tryable({})]", what),
+        )
+    };
+    let (output_variant, output_ty) = output.ok_or_else(|| missing("output"))?;
+    let (residual_variant, residual_ty) = residual.ok_or_else(|| missing("residual"))?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::iteritor::try_polyfill::Try for #name #ty_generics #where_clause {
+            type Output = #output_ty;
+            type Residual = ::core::result::Result<::core::convert::Infallible, #residual_ty>;
+
+            fn from_output(output: Self::Output) -> Self {
+                #name::#output_variant(output)
+            }
+            fn branch(self) -> ::core::ops::ControlFlow<Self::Residual, Self::Output> {
+                match self {
+                    #name::#output_variant(output) => ::core::ops::ControlFlow::Continue(output),
+                    #name::#residual_variant(residual) => {
+                        ::core::ops::ControlFlow::Break(::core::result::Result::Err(residual))
+                    }
+                }
+            }
+        }
+
+        impl #impl_generics ::iteritor::try_polyfill::FromResidual<
+            ::core::result::Result<::core::convert::Infallible, #residual_ty>
+        > for #name #ty_generics #where_clause {
+            fn from_residual(
+                residual: ::core::result::Result<::core::convert::Infallible, #residual_ty>,
+            ) -> Self {
+                match residual {
+                    ::core::result::Result::Err(residual) => #name::#residual_variant(residual),
+                    ::core::result::Result::Ok(never) => match never {},
+                }
+            }
+        }
+    })
+}
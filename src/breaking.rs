@@ -14,13 +14,22 @@
  limitations under the License.
  */
 
-use core::ops::ControlFlow;
+use core::{
+    fmt,
+    iter::{FusedIterator, Peekable},
+    ops::ControlFlow,
+};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 
 use crate::try_polyfill::Try;
 
-pub struct BreakingIterator<'a, Input, Residual> {
+pub struct BreakingIterator<'a, Input, Residual, Observer = fn(&Residual)> {
     input_iter: Input,
     result: &'a mut Option<Residual>,
+    observer: Observer,
+    consumed: usize,
 }
 
 impl<'a, I, T, R> BreakingIterator<'a, I, R>
@@ -29,24 +38,102 @@ where
     T: Try<Residual = R>,
 {
     pub fn new(input_iter: I, result: &'a mut Option<R>) -> Self {
-        Self { input_iter, result }
+        Self::new_with_observer(input_iter, result, |_| {})
+    }
+}
+
+impl<'a, I, T, R, O> BreakingIterator<'a, I, R, O>
+where
+    I: Iterator<Item = T>,
+    T: Try<Residual = R>,
+    O: FnMut(&R),
+{
+    /// As [`BreakingIterator::new`], but calls `observer` with the residual
+    /// the moment the input turns up a divergent value, just before it's
+    /// recorded. That only happens once, since nothing more is pulled after
+    /// it. A break made with [`BreakingIterator::break_with`] isn't
+    /// observed.
+    pub fn new_with_observer(input_iter: I, result: &'a mut Option<R>, observer: O) -> Self {
+        Self {
+            input_iter,
+            result,
+            observer,
+            consumed: 0,
+        }
+    }
+
+    fn record(&mut self, residual: R) {
+        (self.observer)(&residual);
+        self.result.replace(residual);
+    }
+
+    /// How many items have been pulled from the input so far, counting the
+    /// divergent value that caused a break, if there was one. Consuming
+    /// this with `fold` doesn't keep count, since nothing could read it.
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+
+    /// The most items this can still yield: none once a break has been
+    /// recorded, otherwise however many the input has left.
+    pub fn remaining_upper_bound(&self) -> Option<usize> {
+        self.size_hint().1
+    }
+
+    /// Whether a break has been recorded, as opposed to the input just
+    /// running out (or not having been consumed yet).
+    ///
+    /// A break is only recorded when pulling an item turns up a divergent
+    /// value, so this stays false right up until the `next` (or fold) that
+    /// hits one and returns `None`. To check it in the middle of a fold,
+    /// pull with `while let Some(item) = iter.next()` rather than a `for`
+    /// loop, which holds on to the iterator until it's done.
+    pub fn broke(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// The residual recorded by a break, if there has been one.
+    pub fn residual(&self) -> Option<&R> {
+        self.result.as_ref()
+    }
+
+    /// Records `residual` as the break result, as if the input had produced a
+    /// divergent value, so this iterator yields nothing more. Does nothing if
+    /// a break has already been recorded.
+    pub fn break_with(&mut self, residual: R) {
+        if self.result.is_none() {
+            self.result.replace(residual);
+        }
+    }
+}
+
+impl<'a, I, R: fmt::Debug, O> fmt::Debug for BreakingIterator<'a, I, R, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BreakingIterator")
+            .field("input_iter", &format_args!(".."))
+            .field("result", &self.result)
+            .finish()
     }
 }
 
-impl<'a, I, T, R> Iterator for BreakingIterator<'a, I, R>
+impl<'a, I, T, R, O> Iterator for BreakingIterator<'a, I, R, O>
 where
     I: Iterator<Item = T>,
     T: Try<Residual = R>,
+    O: FnMut(&R),
 {
     type Item = T::Output;
+    #[inline]
     fn next(&mut self) -> Option<T::Output> {
         use ControlFlow::*;
         // always return None after we've found a break result
         if self.result.is_none() {
-            match self.input_iter.next().map(T::branch)? {
+            let next = self.input_iter.next()?;
+            self.consumed += 1;
+            match next.branch() {
                 Continue(next) => Some(next),
                 Break(residual) => {
-                    self.result.replace(residual);
+                    self.record(residual);
                     None
                 }
             }
@@ -57,7 +144,389 @@ where
     fn size_hint(&self) -> (usize, Option<usize>) {
         // since this is a filtering iterator, we may give out
         // fewer than our input iterator puts out.
+        if self.result.is_some() {
+            return (0, Some(0));
+        }
         let (_, high) = self.input_iter.size_hint();
         (0, high)
     }
+    // these go through the input's own try_fold, so adapters like `sum`
+    // get its internal iteration rather than a `next` call per item.
+    fn fold<A, F>(mut self, init: A, mut f: F) -> A
+    where
+        F: FnMut(A, T::Output) -> A,
+    {
+        if self.result.is_some() {
+            return init;
+        }
+        let flow = self
+            .input_iter
+            .try_fold(init, |acc, next| match next.branch() {
+                ControlFlow::Continue(next) => ControlFlow::Continue(f(acc, next)),
+                ControlFlow::Break(residual) => ControlFlow::Break((acc, residual)),
+            });
+        match flow {
+            ControlFlow::Continue(acc) => acc,
+            ControlFlow::Break((acc, residual)) => {
+                self.record(residual);
+                acc
+            }
+        }
+    }
+    #[cfg(feature = "nightly")]
+    fn try_fold<A, F, Q>(&mut self, init: A, mut f: F) -> Q
+    where
+        F: FnMut(A, T::Output) -> Q,
+        Q: core::ops::Try<Output = A>,
+    {
+        if self.result.is_some() {
+            return Q::from_output(init);
+        }
+        let consumed = &mut self.consumed;
+        let flow = self.input_iter.try_fold(init, |acc, next| {
+            *consumed += 1;
+            match next.branch() {
+                ControlFlow::Continue(next) => match f(acc, next).branch() {
+                    ControlFlow::Continue(acc) => ControlFlow::Continue(acc),
+                    ControlFlow::Break(residual) => ControlFlow::Break(Err(residual)),
+                },
+                ControlFlow::Break(residual) => ControlFlow::Break(Ok((acc, residual))),
+            }
+        });
+        match flow {
+            ControlFlow::Continue(acc) => Q::from_output(acc),
+            ControlFlow::Break(Ok((acc, residual))) => {
+                self.record(residual);
+                Q::from_output(acc)
+            }
+            ControlFlow::Break(Err(residual)) => Q::from_residual(residual),
+        }
+    }
+}
+
+/// Iterator over the 'normal' values at the front of a [`Peekable`], which
+/// stops at the first divergent value without taking it. See
+/// [`crate::PeekableFolding::with_folding_ref`].
+pub struct NormalPrefix<'a, I: Iterator> {
+    input_iter: &'a mut Peekable<I>,
+}
+
+impl<'a, I, T> NormalPrefix<'a, I>
+where
+    I: Iterator<Item = T>,
+    T: Try + Clone,
+{
+    pub fn new(input_iter: &'a mut Peekable<I>) -> Self {
+        Self { input_iter }
+    }
+}
+
+impl<'a, I, T> Iterator for NormalPrefix<'a, I>
+where
+    I: Iterator<Item = T>,
+    T: Try + Clone,
+{
+    type Item = T::Output;
+    fn next(&mut self) -> Option<T::Output> {
+        let next = self
+            .input_iter
+            .next_if(|next| matches!(next.clone().branch(), ControlFlow::Continue(_)))?;
+        match next.branch() {
+            ControlFlow::Continue(next) => Some(next),
+            // only if the clone branched differently to the original, which
+            // leaves nothing sensible to do but stop.
+            ControlFlow::Break(_) => None,
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_, high) = self.input_iter.size_hint();
+        (0, high)
+    }
+}
+
+/// How a fold from [`crate::IterFolding::with_folding_try`] ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TryFolded<B, C, D> {
+    /// The fold stopped itself early, with this value.
+    Stopped(B),
+    /// The fold ran to the end of its input, with this result.
+    Completed(C),
+    /// The input had a divergent value, which is given back.
+    Diverged(D),
+}
+
+/// Like [`BreakingIterator`], but holds the break result itself rather than
+/// borrowing somewhere to put it, for use on its own (without
+/// [`crate::IterFolding::with_folding`]). Once it's done with, the result can
+/// be had from [`OwnedBreakingIterator::into_result`].
+pub struct OwnedBreakingIterator<Input, Residual> {
+    input_iter: Input,
+    result: Option<Residual>,
+}
+
+impl<I, T, R> OwnedBreakingIterator<I, R>
+where
+    I: Iterator<Item = T>,
+    T: Try<Residual = R>,
+{
+    pub fn new(input_iter: I) -> Self {
+        Self {
+            input_iter,
+            result: None,
+        }
+    }
+
+    /// Whether a break has been recorded, as opposed to the input just
+    /// running out (or not having been consumed yet).
+    pub fn broke(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// The residual recorded by a break, if there has been one.
+    pub fn residual(&self) -> Option<&R> {
+        self.result.as_ref()
+    }
+
+    /// Gives back the residual recorded by a break, if there was one.
+    pub fn into_result(self) -> Option<R> {
+        self.result
+    }
+
+    fn borrowed(&mut self) -> BreakingIterator<'_, &mut I, R> {
+        BreakingIterator::new(&mut self.input_iter, &mut self.result)
+    }
+}
+
+impl<I, R: fmt::Debug> fmt::Debug for OwnedBreakingIterator<I, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedBreakingIterator")
+            .field("input_iter", &format_args!(".."))
+            .field("result", &self.result)
+            .finish()
+    }
+}
+
+impl<I, T, R> Iterator for OwnedBreakingIterator<I, R>
+where
+    I: Iterator<Item = T>,
+    T: Try<Residual = R>,
+{
+    type Item = T::Output;
+    #[inline]
+    fn next(&mut self) -> Option<T::Output> {
+        self.borrowed().next()
+    }
+    fn fold<A, F>(mut self, init: A, f: F) -> A
+    where
+        F: FnMut(A, T::Output) -> A,
+    {
+        self.borrowed().fold(init, f)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.result.is_some() {
+            return (0, Some(0));
+        }
+        let (_, high) = self.input_iter.size_hint();
+        (0, high)
+    }
+}
+
+impl<I, T, R> FusedIterator for OwnedBreakingIterator<I, R>
+where
+    I: FusedIterator<Item = T>,
+    T: Try<Residual = R>,
+{
+}
+
+/// Like [`BreakingIterator`], but rather than stopping at the first divergent
+/// value it collects the residual of every one of them and carries on.
+#[cfg(feature = "alloc")]
+pub struct CollectingIterator<'a, Input, Residual> {
+    input_iter: Input,
+    divergents: &'a mut Vec<Residual>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, I, T, R> CollectingIterator<'a, I, R>
+where
+    I: Iterator<Item = T>,
+    T: Try<Residual = R>,
+{
+    pub fn new(input_iter: I, divergents: &'a mut Vec<R>) -> Self {
+        Self {
+            input_iter,
+            divergents,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, I, T, R> Iterator for CollectingIterator<'a, I, R>
+where
+    I: Iterator<Item = T>,
+    T: Try<Residual = R>,
+{
+    type Item = T::Output;
+    #[inline]
+    fn next(&mut self) -> Option<T::Output> {
+        use ControlFlow::*;
+        for next in &mut self.input_iter {
+            match next.branch() {
+                Continue(next) => return Some(next),
+                Break(residual) => self.divergents.push(residual),
+            }
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // since this is a filtering iterator, we may give out
+        // fewer than our input iterator puts out.
+        let (_, high) = self.input_iter.size_hint();
+        (0, high)
+    }
+}
+
+// once a break is recorded this never yields again, but before that it's
+// only as fused as its input.
+impl<'a, I, T, R, O> FusedIterator for BreakingIterator<'a, I, R, O>
+where
+    I: FusedIterator<Item = T>,
+    T: Try<Residual = R>,
+    O: FnMut(&R),
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filtered::tests::result_samples;
+
+    fn assert_fused<I: FusedIterator>(iter: I) -> I {
+        iter
+    }
+
+    #[test]
+    fn breaking_stays_exhausted_after_break() {
+        let mut result = None;
+        let mut iter = assert_fused(BreakingIterator::new(result_samples(), &mut result));
+
+        assert_eq!(iter.by_ref().count(), 3);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+        assert_eq!(result, Some(Err("boom")));
+    }
+
+    #[test]
+    fn owned_breaking_into_result() {
+        let mut iter = assert_fused(OwnedBreakingIterator::new(result_samples()));
+
+        assert_eq!(iter.by_ref().sum::<u32>(), 4);
+        assert!(iter.broke());
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.into_result(), Some(Err("boom")));
+
+        let mut iter = OwnedBreakingIterator::new(result_samples().take(3));
+        assert_eq!(iter.by_ref().count(), 3);
+        assert_eq!(iter.into_result(), None);
+    }
+
+    #[test]
+    fn breaking_fold_records_break() {
+        let mut result = None;
+        let iter = BreakingIterator::new(result_samples(), &mut result);
+        assert_eq!(iter.fold(0, |acc, n| acc * 10 + n), 121);
+        assert_eq!(result, Some(Err("boom")));
+
+        let mut input = result_samples();
+        let mut result = None;
+        let mut iter = BreakingIterator::new(input.by_ref(), &mut result);
+        assert_eq!(iter.try_fold(0u32, |acc, n| acc.checked_add(n)), Some(4));
+        assert_eq!(iter.by_ref().fold(10, |acc, n| acc * 10 + n), 10);
+        assert_eq!(iter.try_fold(10u32, |acc, n| acc.checked_add(n)), Some(10));
+        assert_eq!(result, Some(Err("boom")));
+        assert_eq!(input.next(), Some(Err("hi")));
+
+        let mut result = None;
+        let mut iter = BreakingIterator::new(result_samples(), &mut result);
+        assert_eq!(iter.try_fold(0u32, |acc, n| acc.checked_sub(n)), None);
+        assert_eq!(iter.next(), Some(2));
+    }
+
+    #[test]
+    fn breaking_counts_consumed() {
+        let mut result = None;
+        let mut iter = BreakingIterator::new(result_samples(), &mut result);
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.consumed(), 1);
+        assert_eq!(iter.try_fold(0u32, |acc, n| acc.checked_add(n)), Some(3));
+        assert_eq!(iter.consumed(), 4);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.consumed(), 4);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn breaking_observer_fires_once() {
+        let mut result = None;
+        let mut seen = Vec::new();
+        let iter =
+            BreakingIterator::new_with_observer(result_samples(), &mut result, |r| seen.push(*r));
+        assert_eq!(iter.sum::<u32>(), 4);
+        assert_eq!(seen, [Err("boom")]);
+        assert_eq!(result, Some(Err("boom")));
+
+        let mut result = None;
+        let mut fired = 0;
+        let mut iter =
+            BreakingIterator::new_with_observer(result_samples(), &mut result, |_| fired += 1);
+        assert_eq!(iter.by_ref().count(), 3);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.by_ref().fold(0, |acc, n| acc * 10 + n), 0);
+        drop(iter);
+        assert_eq!(fired, 1);
+    }
+
+    #[test]
+    fn breaking_exposes_residual() {
+        let mut result = None;
+        let mut iter = BreakingIterator::new(result_samples(), &mut result);
+        assert!(!iter.broke());
+        assert_eq!(iter.residual(), None);
+
+        assert_eq!(iter.by_ref().take(3).count(), 3);
+        assert!(!iter.broke());
+
+        assert_eq!(iter.next(), None);
+        assert!(iter.broke());
+        assert_eq!(iter.residual(), Some(&Err("boom")));
+    }
+
+    #[test]
+    fn breaking_size_hint_after_break() {
+        let mut result = None;
+        let mut iter = BreakingIterator::new(result_samples(), &mut result);
+        assert_eq!(iter.size_hint(), (0, Some(9)));
+        assert_eq!(iter.remaining_upper_bound(), Some(9));
+
+        iter.by_ref().for_each(drop);
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(iter.remaining_upper_bound(), Some(0));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn breaking_debug_shows_result() {
+        let mut result = None;
+        let mut iter = BreakingIterator::new(result_samples(), &mut result);
+        assert_eq!(
+            format!("{:?}", iter),
+            "BreakingIterator { input_iter: .., result: None }"
+        );
+
+        iter.by_ref().for_each(drop);
+        assert_eq!(
+            format!("{:?}", iter),
+            r#"BreakingIterator { input_iter: .., result: Some(Err("boom")) }"#
+        );
+    }
 }
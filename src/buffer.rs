@@ -14,21 +14,104 @@
  limitations under the License.
  */
 
-use core::ops::ControlFlow;
+use core::{
+    cell::{Cell, RefCell, UnsafeCell},
+    convert::Infallible,
+    mem::MaybeUninit,
+    ops::ControlFlow,
+};
 
-use crate::try_polyfill::{FromResidual, Try};
+use crate::residual::{FromResidualMap, ResidualMap};
+use crate::try_polyfill::Try;
 
-pub trait ControlFlowBuffer: Default + Clone {
+/// A queue used to hold divergent values while the normal values are being
+/// processed elsewhere.
+///
+/// The filtering and recombining iterators each hold a clone of the buffer,
+/// so cloning must produce another handle to the *same* underlying queue
+/// (like `Rc` does), not an independent copy. Buffers that live on the stack
+/// implement this trait for a reference to themselves instead, and a queue
+/// that can't be cloned can be a [`QueueBackend`] shared with [`CellBuffer`]
+/// (or `SharedBuffer` with the `alloc` feature). The items
+/// never get cloned along with it, so they don't need to be `Clone` (eg.
+/// `anyhow::Error`), except to use [`ControlFlowBuffer::peek_front`].
+///
+/// The buffers in this crate never run the caller's code (ie. the source
+/// iterator or the inner chain) while in the middle of an operation, so a
+/// panic from that code leaves them consistent and usable: the divergent
+/// values pulled before the panic stay queued, and only the item being
+/// handled when it happened is lost. The same goes for a panicking `clone`
+/// in [`ControlFlowBuffer::peek_front`], which leaves the buffer unchanged.
+pub trait ControlFlowBuffer: Clone {
     type Item;
 
     /// Add an item to the buffer queue
     fn push(&self, input: Self::Item);
     /// Remove an item from the buffer queue
     fn pop(&self) -> Option<Self::Item>;
+    /// Get a copy of the item at the front of the buffer queue without
+    /// removing it. By default this pops it and pushes it back, then moves
+    /// everything else back behind it, so buffers that can get at their
+    /// front item directly should override it.
+    fn peek_front(&self) -> Option<Self::Item>
+    where
+        Self::Item: Clone,
+    {
+        let rest = self.len().saturating_sub(1);
+        let front = PutBack(self, rest, self.pop());
+        front.2.clone()
+    }
+    /// The number of items currently in the buffer queue
+    fn len(&self) -> usize;
+    /// Whether the buffer queue is currently empty
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Hint that about `additional` more items are likely to be pushed, for
+    /// buffers that can allocate ahead of time. Does nothing by default.
+    fn reserve(&self, _additional: usize) {}
+    /// Add each of `items` to the back of the buffer queue, in order. Useful
+    /// for seeding a buffer with divergent values left over from an earlier
+    /// run, so they come out ahead of anything from the next one.
+    fn extend_back(&self, items: impl IntoIterator<Item = Self::Item>) {
+        for item in items {
+            self.push(item)
+        }
+    }
+    /// Remove all items from the buffer queue
+    fn clear(&self) {
+        while self.pop().is_some() {}
+    }
+    /// Pops items off the buffer queue until it's empty. Useful for getting
+    /// at anything left over after a pipeline stops early.
+    fn drain(&self) -> Drain<'_, Self> {
+        Drain(self)
+    }
+    /// Called by [`ControlFlowBuffer::next_unwrapped`] for every divergent
+    /// value it comes across, whether or not it ends up buffered. Does
+    /// nothing by default.
+    fn count_divergent(&self) {}
+    /// How many divergent values have been found so far, for buffers that
+    /// keep count (see [`ControlFlowBuffer::count_divergent`]).
+    fn divergents_seen(&self) -> Option<usize> {
+        None
+    }
+    /// Whether the buffer is too full to take any more items. While this is
+    /// true, [`ControlFlowBuffer::next_unwrapped`] stops advancing its input,
+    /// which the filtering iterator takes as the end of it. So once a buffer
+    /// saturates it should stay that way, and make sure that shows up in the
+    /// output somehow (as `BoundedBuffer` does), rather than have it look like
+    /// the source ran out.
+    fn is_saturated(&self) -> bool {
+        false
+    }
 
     /// Both push and pop with an optimization that we don't
     /// bother putting it in the queue if we'd just be popping
-    /// this value off anyways.
+    /// this value off anyways. [`ControlFlowBuffer::next_wrapped`] calls
+    /// this for every item the iterator gives, so buffers that can do it
+    /// in one step can override it.
+    #[inline]
     fn push_and_pop(&self, input: Self::Item) -> Self::Item {
         if let Some(next) = self.pop() {
             self.push(input);
@@ -41,60 +124,2008 @@ pub trait ControlFlowBuffer: Default + Clone {
     /// Advances the given iterator until an item that unwraps is found,
     /// and returns that. Any items found in the meantime will be added to
     /// the buffer. This returns None when no more items are available
-    /// (As with [`Iterator::next`]).
+    /// (As with [`Iterator::next`]), or when the buffer is saturated.
+    ///
+    /// Divergent values are buffered as they came, rebuilt from their
+    /// residual with [`crate::try_polyfill::FromResidual`]. To buffer them
+    /// as some other type, with an error conversion say, use
+    /// [`ControlFlowBuffer::next_unwrapped_map`] with a
+    /// [`crate::residual::ConvertResidual`]: the residual is converted right
+    /// there, before it's pushed.
+    #[inline]
     fn next_unwrapped<O>(&self, iter: &mut impl Iterator<Item = Self::Item>) -> Option<O>
     where
         Self::Item: Try<Output = O>,
     {
-        for next in iter {
+        self.next_unwrapped_map(iter, &mut FromResidualMap)
+    }
+
+    /// As [`ControlFlowBuffer::next_unwrapped`], but the iterator can be of
+    /// some other [`Try`] type, with `map` deciding what (if anything) gets
+    /// buffered for each of its divergent values.
+    #[inline]
+    fn next_unwrapped_map<S, M>(
+        &self,
+        iter: &mut impl Iterator<Item = S>,
+        map: &mut M,
+    ) -> Option<S::Output>
+    where
+        S: Try,
+        M: ResidualMap<S, Output = Self::Item>,
+    {
+        while !self.is_saturated() {
+            let next = iter.next()?;
             use ControlFlow::*;
-            match next.branch() {
+            match map.split(next) {
                 Continue(next) => return Some(next),
-                Break(residual) => self.push(FromResidual::from_residual(residual)),
+                Break(item) => {
+                    self.count_divergent();
+                    if let Some(item) = item {
+                        self.push(item)
+                    }
+                }
             }
         }
         None
     }
 
+    /// As [`ControlFlowBuffer::next_unwrapped`], but pulls up to `n` unwrapped
+    /// values at once, appending them to `out`. This stops early if the
+    /// iterator runs out or the buffer is saturated, and returns how many
+    /// values were added.
+    #[cfg(feature = "alloc")]
+    fn next_unwrapped_batch<O>(
+        &self,
+        iter: &mut impl Iterator<Item = Self::Item>,
+        out: &mut alloc::vec::Vec<O>,
+        n: usize,
+    ) -> usize
+    where
+        Self::Item: Try<Output = O>,
+    {
+        out.reserve(n);
+        let start = out.len();
+        while out.len() - start < n {
+            match self.next_unwrapped(iter) {
+                Some(next) => out.push(next),
+                None => break,
+            }
+        }
+        out.len() - start
+    }
+
     /// Returns the next item out of the buffer, or if there aren't any,
     /// advances the given iterator and returns either the first item buffered
     /// as a result of that or the item returned from the iterator if none
-    /// were buffered.
+    /// were buffered. If the iterator runs out, anything it left in the
+    /// buffer on the way is still returned.
+    #[inline]
     fn next_wrapped<O>(&self, iter: &mut impl Iterator<Item = O>) -> Option<Self::Item>
     where
         Self::Item: Try<Output = O>,
     {
-        if let Some(next) = self.pop() {
-            Some(next)
+        self.next_wrapped_tracked(iter, &mut false)
+    }
+
+    /// As [`ControlFlowBuffer::next_wrapped`], but skips checking the buffer
+    /// before advancing the iterator when `drained` says the last call left
+    /// it empty, and updates `drained` for the next call. Since the buffer
+    /// only gains items while the iterator is being advanced, this saves a
+    /// pop per item on runs of normal values. Anything pushed to the buffer
+    /// from elsewhere between calls (eg. with
+    /// [`ControlFlowBuffer::extend_back`]) still comes out next, ahead of
+    /// what the iterator gives, but the iterator is advanced once before it
+    /// does.
+    #[inline]
+    fn next_wrapped_tracked<O>(
+        &self,
+        iter: &mut impl Iterator<Item = O>,
+        drained: &mut bool,
+    ) -> Option<Self::Item>
+    where
+        Self::Item: Try<Output = O>,
+    {
+        if !*drained {
+            if let Some(next) = self.pop() {
+                return Some(next);
+            }
+        }
+        match iter.next() {
+            Some(next) => {
+                let next = self.push_and_pop(Self::Item::from_output(next));
+                *drained = self.is_empty();
+                Some(next)
+            }
+            None => {
+                let buffered = self.pop();
+                *drained = buffered.is_none();
+                buffered
+            }
+        }
+    }
+}
+
+/// Marker for buffers that never stop being saturated once they are (if
+/// they ever are), so a filtering iterator over one stops for good once its
+/// input does.
+pub trait FusedBuffer: ControlFlowBuffer {}
+
+/// Buffers that can make an independent copy of themselves, as opposed to
+/// `clone`, which makes another handle to the same queue.
+pub trait ForkBuffer: ControlFlowBuffer {
+    /// Creates a new buffer holding copies of the items in this one.
+    fn fork(&self) -> Self
+    where
+        Self::Item: Clone;
+}
+
+/// Puts an item taken out of a cell back when dropped, so it's restored
+/// even if whatever was done with it in the meantime panics.
+struct Restore<'a, T>(&'a Cell<Option<T>>, Option<T>);
+
+impl<'a, T> Restore<'a, T> {
+    fn take(cell: &'a Cell<Option<T>>) -> Self {
+        Self(cell, cell.take())
+    }
+}
+
+impl<'a, T> Drop for Restore<'a, T> {
+    fn drop(&mut self) {
+        self.0.set(self.1.take())
+    }
+}
+
+/// Pushes an item popped off the front of a buffer back when dropped, and
+/// then moves the `rest` of the queue back behind it, so the buffer ends up
+/// as it was even if whatever was done with the item in the meantime panics.
+struct PutBack<'a, B: ControlFlowBuffer>(&'a B, usize, Option<B::Item>);
+
+impl<'a, B: ControlFlowBuffer> Drop for PutBack<'a, B> {
+    fn drop(&mut self) {
+        if let Some(item) = self.2.take() {
+            self.0.push(item);
+            for _ in 0..self.1 {
+                if let Some(item) = self.0.pop() {
+                    self.0.push(item)
+                }
+            }
+        }
+    }
+}
+
+/// Buffers that can fork every handle to themselves into handles to one new
+/// buffer, so that something holding several of them (like a
+/// [`crate::filtered::DefilteredIter`] and the
+/// [`crate::filtered::FilteredIter`] in its chain) can be cloned as a whole.
+pub trait ForkScope: ForkBuffer {
+    /// Forks this buffer, then calls `f`. Until `f` returns, forking any
+    /// handle to this buffer gives another handle to that same fork rather
+    /// than a new one.
+    fn fork_scope<R>(&self, f: impl FnOnce() -> R) -> (Self, R)
+    where
+        Self::Item: Clone;
+}
+
+/// Iterator returned by [`ControlFlowBuffer::drain`].
+pub struct Drain<'a, B: ?Sized>(&'a B);
+
+impl<'a, B: ControlFlowBuffer> Iterator for Drain<'a, B> {
+    type Item = B::Item;
+    fn next(&mut self) -> Option<B::Item> {
+        self.0.pop()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.len();
+        (len, Some(len))
+    }
+}
+
+/// A plain FIFO queue that [`CellBuffer`] can turn into a
+/// [`ControlFlowBuffer`], taking care of sharing it between iterators.
+pub trait QueueBackend {
+    type Item;
+
+    /// Add an item to the back of the queue, handing it back if the queue
+    /// is full
+    fn push_back(&mut self, input: Self::Item) -> Result<(), Self::Item>;
+    /// Remove the item at the front of the queue
+    fn pop_front(&mut self) -> Option<Self::Item>;
+    /// The item at the front of the queue
+    fn front(&self) -> Option<&Self::Item>;
+    /// The number of items in the queue
+    fn len(&self) -> usize;
+    /// Whether the queue is empty
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Make room for about `additional` more items, if the queue can.
+    /// Does nothing by default.
+    fn reserve(&mut self, _additional: usize) {}
+    /// Remove all items from the queue
+    fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+    /// The most items the queue can ever hold, if it has a fixed limit.
+    /// This is only used to say what the limit was when it's overflowed,
+    /// and is `None` by default.
+    fn capacity(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// A buffer over any [`QueueBackend`], shared by reference.
+///
+/// [`ControlFlowBuffer`] is implemented for `&CellBuffer`, so the buffer
+/// itself needs to outlive the iterators using it. Pushing into a full
+/// queue panics.
+pub struct CellBuffer<Q>(RefCell<Q>);
+
+impl<Q> CellBuffer<Q> {
+    pub const fn with_queue(queue: Q) -> Self {
+        Self(RefCell::new(queue))
+    }
+
+    /// Takes the queue back out of the buffer.
+    pub fn into_inner(self) -> Q {
+        self.0.into_inner()
+    }
+}
+
+impl<Q: Default> Default for CellBuffer<Q> {
+    fn default() -> Self {
+        Self::with_queue(Q::default())
+    }
+}
+
+impl<Q: QueueBackend> ControlFlowBuffer for &CellBuffer<Q> {
+    type Item = Q::Item;
+
+    fn push(&self, input: Q::Item) {
+        let mut queue = self.0.borrow_mut();
+        if queue.push_back(input).is_err() {
+            match queue.capacity() {
+                Some(capacity) => panic!(
+                    "CellBuffer overflowed: more than {} consecutive divergent values",
+                    capacity
+                ),
+                None => {
+                    panic!("CellBuffer overflowed: its queue can't hold any more divergent values")
+                }
+            }
+        }
+    }
+    fn pop(&self) -> Option<Q::Item> {
+        self.0.borrow_mut().pop_front()
+    }
+    fn peek_front(&self) -> Option<Q::Item>
+    where
+        Q::Item: Clone,
+    {
+        self.0.borrow().front().cloned()
+    }
+    fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+    fn reserve(&self, additional: usize) {
+        self.0.borrow_mut().reserve(additional)
+    }
+    fn clear(&self) {
+        self.0.borrow_mut().clear()
+    }
+}
+
+impl<Q: QueueBackend> FusedBuffer for &CellBuffer<Q> {}
+
+/// A fixed-capacity buffer backed by an array of `N` slots, for use where
+/// no allocator is available.
+///
+/// [`ControlFlowBuffer`] is implemented for `&ArrayBuffer`, so the buffer
+/// itself needs to outlive the iterators using it.
+///
+/// Pushing more than `N` items at once (ie. a run of more than `N` divergent
+/// values) panics. Use [`ArrayBuffer::try_push`] if you need to check.
+pub struct ArrayBuffer<T, const N: usize> {
+    slots: [Cell<Option<T>>; N],
+    head: Cell<usize>,
+    len: Cell<usize>,
+}
+
+impl<T, const N: usize> ArrayBuffer<T, N> {
+    pub fn new() -> Self {
+        Self {
+            slots: [(); N].map(|_| Cell::new(None)),
+            head: Cell::new(0),
+            len: Cell::new(0),
+        }
+    }
+
+    /// Adds an item to the buffer, handing it back if the buffer is full.
+    pub fn try_push(&self, input: T) -> Result<(), T> {
+        let len = self.len.get();
+        if len == N {
+            return Err(input);
+        }
+        self.slots[(self.head.get() + len) % N].set(Some(input));
+        self.len.set(len + 1);
+        Ok(())
+    }
+}
+
+impl<T, const N: usize> Default for ArrayBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> ControlFlowBuffer for &ArrayBuffer<T, N> {
+    type Item = T;
+
+    fn push(&self, input: T) {
+        if self.try_push(input).is_err() {
+            panic!(
+                "ArrayBuffer overflowed: more than {} consecutive divergent values",
+                N
+            );
+        }
+    }
+    fn pop(&self) -> Option<T> {
+        let len = self.len.get();
+        if len == 0 {
+            return None;
+        }
+        let head = self.head.get();
+        self.head.set((head + 1) % N);
+        self.len.set(len - 1);
+        self.slots[head].take()
+    }
+    fn peek_front(&self) -> Option<T>
+    where
+        T: Clone,
+    {
+        if self.len.get() == 0 {
+            return None;
+        }
+        Restore::take(&self.slots[self.head.get()]).1.clone()
+    }
+    fn len(&self) -> usize {
+        self.len.get()
+    }
+}
+
+impl<T, const N: usize> FusedBuffer for &ArrayBuffer<T, N> {}
+
+/// A fixed-capacity ring buffer that, rather than panicking when full,
+/// overwrites the oldest buffered item.
+///
+/// This keeps the order of the surviving items intact, but any value pushed
+/// past the capacity costs you the oldest one. The number of items lost this
+/// way is available from [`RingBuffer::dropped_count`].
+///
+/// As with [`ArrayBuffer`], [`ControlFlowBuffer`] is implemented for
+/// `&RingBuffer`.
+pub struct RingBuffer<T, const N: usize> {
+    slots: [UnsafeCell<MaybeUninit<T>>; N],
+    head: Cell<usize>,
+    len: Cell<usize>,
+    dropped: Cell<usize>,
+}
+
+impl<T, const N: usize> RingBuffer<T, N> {
+    pub fn new() -> Self {
+        Self {
+            slots: [(); N].map(|_| UnsafeCell::new(MaybeUninit::uninit())),
+            head: Cell::new(0),
+            len: Cell::new(0),
+            dropped: Cell::new(0),
+        }
+    }
+
+    /// The number of items that have been overwritten because the buffer
+    /// was full.
+    pub fn dropped_count(&self) -> usize {
+        self.dropped.get()
+    }
+
+    fn take_front(&self) -> Option<T> {
+        let len = self.len.get();
+        if len == 0 {
+            return None;
+        }
+        let head = self.head.get();
+        self.head.set((head + 1) % N);
+        self.len.set(len - 1);
+        // SAFETY: slots in head..head+len are always initialized, and we've
+        // already moved head past this one so it won't be read again.
+        Some(unsafe { (*self.slots[head].get()).as_ptr().read() })
+    }
+}
+
+impl<T, const N: usize> Default for RingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for RingBuffer<T, N> {
+    fn drop(&mut self) {
+        while self.take_front().is_some() {}
+    }
+}
+
+impl<T, const N: usize> ControlFlowBuffer for &RingBuffer<T, N> {
+    type Item = T;
+
+    fn push(&self, input: T) {
+        self.dropped
+            .set(self.dropped.get() + usize::from(self.len.get() == N));
+        if N == 0 {
+            return;
+        }
+        // the overwritten item is only dropped once we're done touching the
+        // buffer, so its Drop can't observe the buffer half-updated.
+        let overwritten = if self.len.get() == N {
+            self.take_front()
         } else {
-            iter.next()
-                .map(|next| self.push_and_pop(Self::Item::from_output(next)))
+            None
+        };
+        let len = self.len.get();
+        let tail = (self.head.get() + len) % N;
+        // SAFETY: the tail slot is outside head..head+len, so it is
+        // uninitialized (or was just moved out of) and nothing refers to it.
+        unsafe { (*self.slots[tail].get()).as_mut_ptr().write(input) };
+        self.len.set(len + 1);
+        drop(overwritten);
+    }
+    fn pop(&self) -> Option<T> {
+        self.take_front()
+    }
+    fn peek_front(&self) -> Option<T>
+    where
+        T: Clone,
+    {
+        if self.len.get() == 0 {
+            return None;
+        }
+        // SAFETY: the head slot is initialized whenever len is non-zero, and
+        // the shared reference doesn't outlive this call.
+        Some(unsafe { (*(*self.slots[self.head.get()].get()).as_ptr()).clone() })
+    }
+    fn len(&self) -> usize {
+        self.len.get()
+    }
+}
+
+impl<T, const N: usize> FusedBuffer for &RingBuffer<T, N> {}
+
+/// A buffer that never holds anything, for filtering where the divergent
+/// values are dealt with some other way (see
+/// [`crate::IterFiltered::with_filtered_sink`]). Its items can't exist, so
+/// nothing can ever be pushed to it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoBuffer;
+
+impl ControlFlowBuffer for NoBuffer {
+    type Item = Infallible;
+
+    fn push(&self, input: Infallible) {
+        match input {}
+    }
+    fn pop(&self) -> Option<Infallible> {
+        None
+    }
+    fn peek_front(&self) -> Option<Infallible> {
+        None
+    }
+    fn len(&self) -> usize {
+        0
+    }
+}
+
+impl FusedBuffer for NoBuffer {}
+
+/// A buffer that holds at most one item, for sources where divergent values
+/// only ever show up on their own.
+///
+/// [`ControlFlowBuffer`] is implemented for `&SlotBuffer`. Consecutive
+/// divergents are not supported: pushing while the slot is full panics. Note
+/// that this also covers divergents separated only by normal values the
+/// inner iterator chain throws away, since those are buffered together too.
+pub struct SlotBuffer<T>(Cell<Option<T>>);
+
+impl<T> SlotBuffer<T> {
+    pub const fn new() -> Self {
+        Self(Cell::new(None))
+    }
+}
+
+impl<T> Default for SlotBuffer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ControlFlowBuffer for &SlotBuffer<T> {
+    type Item = T;
+
+    fn push(&self, input: T) {
+        if let Some(existing) = self.0.replace(Some(input)) {
+            // put it back so the buffer isn't left holding the wrong item.
+            self.0.set(Some(existing));
+            panic!("SlotBuffer overflowed: consecutive divergents not supported");
         }
     }
+    fn pop(&self) -> Option<T> {
+        self.0.take()
+    }
+    fn peek_front(&self) -> Option<T>
+    where
+        T: Clone,
+    {
+        Restore::take(&self.0).1.clone()
+    }
+    fn len(&self) -> usize {
+        let item = self.0.take();
+        let len = usize::from(item.is_some());
+        self.0.set(item);
+        len
+    }
 }
 
-#[cfg(feature = "std")]
-mod is_std {
+impl<T> FusedBuffer for &SlotBuffer<T> {}
+
+#[cfg(feature = "alloc")]
+mod is_alloc {
     use super::*;
 
-    use core::cell::RefCell;
-    use std::{collections::VecDeque, rc::Rc};
+    use alloc::{collections::VecDeque, rc::Rc};
+    use core::{
+        cell::{Cell, RefCell, UnsafeCell},
+        fmt,
+        ops::Deref,
+    };
+    #[cfg(feature = "std")]
+    use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 
-    pub type Buffer<T> = Rc<RefCell<VecDeque<T>>>;
+    impl<T> QueueBackend for VecDeque<T> {
+        type Item = T;
 
-    impl<T> ControlFlowBuffer for Rc<RefCell<VecDeque<T>>> {
+        fn push_back(&mut self, input: T) -> Result<(), T> {
+            VecDeque::push_back(self, input);
+            Ok(())
+        }
+        fn pop_front(&mut self) -> Option<T> {
+            VecDeque::pop_front(self)
+        }
+        fn front(&self) -> Option<&T> {
+            VecDeque::front(self)
+        }
+        fn len(&self) -> usize {
+            VecDeque::len(self)
+        }
+        fn reserve(&mut self, additional: usize) {
+            VecDeque::reserve(self, additional)
+        }
+        fn clear(&mut self) {
+            VecDeque::clear(self)
+        }
+    }
+
+    /// The default buffer, a shared `VecDeque`. It also keeps count of the
+    /// divergent values it has seen.
+    ///
+    /// This derefs to the underlying `RefCell<VecDeque<T>>` if you need to
+    /// get at the queue directly.
+    pub struct Buffer<T> {
+        queue: Rc<RefCell<VecDeque<T>>>,
+        seen: Rc<Cell<usize>>,
+        // the fork to hand out while inside ForkScope::fork_scope.
+        scoped_fork: Rc<RefCell<Option<Buffer<T>>>>,
+    }
+
+    impl<T> Buffer<T> {
+        /// Creates a buffer already holding `items`, eg. the divergent values
+        /// a previous run left behind. They don't count toward
+        /// [`ControlFlowBuffer::divergents_seen`].
+        pub fn from_items(items: impl IntoIterator<Item = T>) -> Self {
+            let buffer = Self::default();
+            buffer.extend_back(items);
+            buffer
+        }
+
+        fn with_seen(self, seen: usize) -> Self {
+            self.seen.set(seen);
+            self
+        }
+    }
+
+    impl<T> Default for Buffer<T> {
+        fn default() -> Self {
+            Self::from(Rc::default())
+        }
+    }
+
+    impl<T> Clone for Buffer<T> {
+        fn clone(&self) -> Self {
+            Self {
+                queue: self.queue.clone(),
+                seen: self.seen.clone(),
+                scoped_fork: self.scoped_fork.clone(),
+            }
+        }
+    }
+
+    impl<T> Deref for Buffer<T> {
+        type Target = RefCell<VecDeque<T>>;
+        fn deref(&self) -> &Self::Target {
+            &self.queue
+        }
+    }
+
+    impl<T> From<Rc<RefCell<VecDeque<T>>>> for Buffer<T> {
+        fn from(queue: Rc<RefCell<VecDeque<T>>>) -> Self {
+            Self {
+                queue,
+                seen: Rc::default(),
+                scoped_fork: Rc::default(),
+            }
+        }
+    }
+
+    impl<T: fmt::Debug> fmt::Debug for Buffer<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.queue.try_borrow() {
+                Ok(queue) => f.debug_tuple("Buffer").field(&*queue).finish(),
+                Err(_) => f.write_str("Buffer(<borrowed>)"),
+            }
+        }
+    }
+
+    impl<T> ControlFlowBuffer for Buffer<T> {
         type Item = T;
 
         fn push(&self, input: T) {
-            self.borrow_mut().push_back(input)
+            self.queue.push(input)
         }
         fn pop(&self) -> Option<T> {
-            self.borrow_mut().pop_front()
+            self.queue.pop()
+        }
+        fn peek_front(&self) -> Option<T>
+        where
+            T: Clone,
+        {
+            self.queue.peek_front()
+        }
+        fn len(&self) -> usize {
+            self.queue.len()
+        }
+        fn reserve(&self, additional: usize) {
+            self.queue.reserve(additional)
+        }
+        fn clear(&self) {
+            self.queue.clear()
+        }
+        fn count_divergent(&self) {
+            self.seen.set(self.seen.get() + 1)
+        }
+        fn divergents_seen(&self) -> Option<usize> {
+            Some(self.seen.get())
+        }
+    }
+
+    impl<T> FusedBuffer for Buffer<T> {}
+
+    impl<T> ForkBuffer for Buffer<T> {
+        fn fork(&self) -> Self
+        where
+            T: Clone,
+        {
+            if let Some(fork) = &*self.scoped_fork.borrow() {
+                return fork.clone();
+            }
+            Self::from(self.queue.fork()).with_seen(self.seen.get())
+        }
+    }
+
+    /// Puts back whatever fork scope was open before, even if the scope's
+    /// function panics.
+    struct Scope<'a, B>(&'a RefCell<Option<B>>, Option<B>);
+
+    impl<'a, B> Drop for Scope<'a, B> {
+        fn drop(&mut self) {
+            self.0.replace(self.1.take());
         }
     }
-}
 
-#[cfg(not(feature = "std"))]
-mod is_std {}
+    impl<T> ForkScope for Buffer<T> {
+        fn fork_scope<R>(&self, f: impl FnOnce() -> R) -> (Self, R)
+        where
+            T: Clone,
+        {
+            let fork = self.fork();
+            let _scope = Scope(
+                &self.scoped_fork,
+                self.scoped_fork.replace(Some(fork.clone())),
+            );
+            let result = f();
+            (fork, result)
+        }
+    }
+
+    /// A [`Buffer`] that refuses to grow past a fixed number of items, to
+    /// avoid unbounded memory use on long runs of divergent values.
+    ///
+    /// Once it holds `cap` items, `sentinel` is queued up behind them and the
+    /// filtering iterator stops pulling from its source for good, so the
+    /// chain inside `with_filtered_buf` sees the end of its input. The
+    /// recombining iterator gives out everything buffered up to that point,
+    /// then `sentinel`, and then nothing more, so running out of room always
+    /// shows up in the output rather than looking like the source ended.
+    /// Anything after the run that filled the buffer is left in the source
+    /// (which can be passed in with `by_ref` to pick up from there).
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// # use iteritor::IterFiltered;
+    /// # use iteritor::buffer::BoundedBuffer;
+    /// let items = [Err(0), Err(1), Err(2), Ok(3), Ok(4), Err(5), Ok(6)];
+    /// let buf = BoundedBuffer::new(2, Err(-1));
+    /// let output: Vec<_> = items
+    ///     .into_iter()
+    ///     .with_filtered_buf(buf, |i| i.flat_map(|n| [n]))
+    ///     .collect();
+    ///
+    /// assert_eq!(output, [Err(0), Err(1), Err(-1)]);
+    /// ```
+    pub struct BoundedBuffer<T> {
+        queue: Buffer<T>,
+        cap: usize,
+        // taken (and queued) once the buffer fills up.
+        sentinel: Rc<RefCell<Option<T>>>,
+        // the fork to hand out while inside ForkScope::fork_scope.
+        scoped_fork: Rc<RefCell<Option<BoundedBuffer<T>>>>,
+    }
+
+    impl<T> BoundedBuffer<T> {
+        pub fn new(cap: usize, sentinel: T) -> Self {
+            Self {
+                queue: Buffer::default(),
+                cap,
+                sentinel: Rc::new(RefCell::new(Some(sentinel))),
+                scoped_fork: Rc::default(),
+            }
+        }
+    }
+
+    impl<T> Clone for BoundedBuffer<T> {
+        fn clone(&self) -> Self {
+            Self {
+                queue: self.queue.clone(),
+                cap: self.cap,
+                sentinel: self.sentinel.clone(),
+                scoped_fork: self.scoped_fork.clone(),
+            }
+        }
+    }
+
+    impl<T> ControlFlowBuffer for BoundedBuffer<T> {
+        type Item = T;
+
+        fn push(&self, input: T) {
+            self.queue.push(input);
+            if self.len() >= self.cap {
+                if let Some(sentinel) = self.sentinel.borrow_mut().take() {
+                    self.queue.push(sentinel)
+                }
+            }
+        }
+        fn pop(&self) -> Option<T> {
+            self.queue.pop()
+        }
+        fn peek_front(&self) -> Option<T>
+        where
+            T: Clone,
+        {
+            self.queue.peek_front()
+        }
+        fn len(&self) -> usize {
+            self.queue.len()
+        }
+        fn reserve(&self, additional: usize) {
+            self.queue.reserve(additional.min(self.cap))
+        }
+        fn clear(&self) {
+            self.queue.clear()
+        }
+        fn count_divergent(&self) {
+            self.queue.count_divergent()
+        }
+        fn divergents_seen(&self) -> Option<usize> {
+            self.queue.divergents_seen()
+        }
+        fn is_saturated(&self) -> bool {
+            self.sentinel.borrow().is_none()
+        }
+    }
 
-pub use is_std::*;
+    impl<T> FusedBuffer for BoundedBuffer<T> {}
+
+    impl<T> ForkBuffer for BoundedBuffer<T> {
+        fn fork(&self) -> Self
+        where
+            T: Clone,
+        {
+            if let Some(fork) = &*self.scoped_fork.borrow() {
+                return fork.clone();
+            }
+            Self {
+                queue: self.queue.fork(),
+                cap: self.cap,
+                sentinel: Rc::new(RefCell::new(self.sentinel.borrow().clone())),
+                scoped_fork: Rc::default(),
+            }
+        }
+    }
+
+    impl<T> ForkScope for BoundedBuffer<T> {
+        fn fork_scope<R>(&self, f: impl FnOnce() -> R) -> (Self, R)
+        where
+            T: Clone,
+        {
+            let fork = self.fork();
+            let _scope = Scope(
+                &self.scoped_fork,
+                self.scoped_fork.replace(Some(fork.clone())),
+            );
+            let result = f();
+            (fork, result)
+        }
+    }
+
+    /// A [`Buffer`] that gives up once asked to hold more than `max` items,
+    /// as a safety valve against pathological input. The push that would go
+    /// over is dropped, and from then on the buffer reports itself
+    /// saturated for good, so the filtering iterator stops pulling from its
+    /// source. See [`crate::IterFiltered::with_filtered_capped`], which
+    /// swaps the rest of the output for a sentinel when that happens.
+    pub struct CappedBuffer<T> {
+        queue: Buffer<T>,
+        max: usize,
+        overflowed: Rc<Cell<bool>>,
+    }
+
+    impl<T> CappedBuffer<T> {
+        pub fn new(max: usize) -> Self {
+            Self {
+                queue: Buffer::default(),
+                max,
+                overflowed: Rc::default(),
+            }
+        }
+
+        /// Whether a push has been dropped for going over the cap.
+        pub fn overflowed(&self) -> bool {
+            self.overflowed.get()
+        }
+    }
+
+    impl<T> Clone for CappedBuffer<T> {
+        fn clone(&self) -> Self {
+            Self {
+                queue: self.queue.clone(),
+                max: self.max,
+                overflowed: self.overflowed.clone(),
+            }
+        }
+    }
+
+    impl<T> ControlFlowBuffer for CappedBuffer<T> {
+        type Item = T;
+
+        fn push(&self, input: T) {
+            if self.overflowed() || self.len() >= self.max {
+                self.overflowed.set(true)
+            } else {
+                self.queue.push(input)
+            }
+        }
+        fn pop(&self) -> Option<T> {
+            self.queue.pop()
+        }
+        fn peek_front(&self) -> Option<T>
+        where
+            T: Clone,
+        {
+            self.queue.peek_front()
+        }
+        fn len(&self) -> usize {
+            self.queue.len()
+        }
+        fn reserve(&self, additional: usize) {
+            self.queue.reserve(additional.min(self.max))
+        }
+        fn clear(&self) {
+            self.queue.clear()
+        }
+        fn count_divergent(&self) {
+            self.queue.count_divergent()
+        }
+        fn divergents_seen(&self) -> Option<usize> {
+            self.queue.divergents_seen()
+        }
+        fn is_saturated(&self) -> bool {
+            self.overflowed()
+        }
+    }
+
+    impl<T> FusedBuffer for CappedBuffer<T> {}
+
+    /// A buffer that can be shared across threads. Using this in place of
+    /// [`Buffer`] makes the filtering and recombining iterators `Send` when
+    /// `T: Send`, at the cost of locking on every push and pop.
+    #[cfg(feature = "std")]
+    pub type SyncBuffer<T> = Arc<Mutex<VecDeque<T>>>;
+
+    impl<T> ControlFlowBuffer for Rc<RefCell<VecDeque<T>>> {
+        type Item = T;
+
+        fn push(&self, input: T) {
+            self.borrow_mut().push_back(input)
+        }
+        fn pop(&self) -> Option<T> {
+            self.borrow_mut().pop_front()
+        }
+        fn peek_front(&self) -> Option<T>
+        where
+            T: Clone,
+        {
+            self.borrow().front().cloned()
+        }
+        fn len(&self) -> usize {
+            self.borrow().len()
+        }
+        fn reserve(&self, additional: usize) {
+            self.borrow_mut().reserve(additional)
+        }
+        fn clear(&self) {
+            self.borrow_mut().clear()
+        }
+    }
+
+    impl<T> FusedBuffer for Rc<RefCell<VecDeque<T>>> {}
+
+    impl<T> ForkBuffer for Rc<RefCell<VecDeque<T>>> {
+        fn fork(&self) -> Self
+        where
+            T: Clone,
+        {
+            Rc::new(RefCell::new(self.borrow().clone()))
+        }
+    }
+
+    /// Lets a `RefCell<VecDeque>` owned by the caller be used as a buffer
+    /// without reference counting, by passing a reference to it.
+    impl<T> ControlFlowBuffer for &RefCell<VecDeque<T>> {
+        type Item = T;
+
+        fn push(&self, input: T) {
+            self.borrow_mut().push_back(input)
+        }
+        fn pop(&self) -> Option<T> {
+            self.borrow_mut().pop_front()
+        }
+        fn peek_front(&self) -> Option<T>
+        where
+            T: Clone,
+        {
+            self.borrow().front().cloned()
+        }
+        fn len(&self) -> usize {
+            self.borrow().len()
+        }
+        fn reserve(&self, additional: usize) {
+            self.borrow_mut().reserve(additional)
+        }
+        fn clear(&self) {
+            self.borrow_mut().clear()
+        }
+    }
+
+    impl<T> FusedBuffer for &RefCell<VecDeque<T>> {}
+
+    /// Serializes the contents of a [`Buffer`] as a sequence, front first.
+    /// Suitable for use with `#[serde(serialize_with = "...")]`.
+    #[cfg(feature = "serde")]
+    pub fn serialize_buffer<T, S>(buf: &Buffer<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: serde::Serialize,
+        S: serde::Serializer,
+    {
+        serde::Serialize::serialize(&*buf.borrow(), serializer)
+    }
+
+    /// Deserializes a sequence produced by [`serialize_buffer`] into a new
+    /// [`Buffer`], preserving order. Suitable for use with
+    /// `#[serde(deserialize_with = "...")]`.
+    #[cfg(feature = "serde")]
+    pub fn deserialize_buffer<'de, T, D>(deserializer: D) -> Result<Buffer<T>, D::Error>
+    where
+        T: serde::Deserialize<'de>,
+        D: serde::Deserializer<'de>,
+    {
+        let items: VecDeque<T> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(Buffer::from(Rc::new(RefCell::new(items))))
+    }
+
+    /// Locks a [`SyncBuffer`], ignoring poisoning. None of the operations on
+    /// it can panic partway through changing the queue, so a panic elsewhere
+    /// while it was locked can't have left it inconsistent.
+    #[cfg(feature = "std")]
+    fn lock<T>(buffer: &Mutex<VecDeque<T>>) -> MutexGuard<'_, VecDeque<T>> {
+        buffer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[cfg(feature = "std")]
+    impl<T> ControlFlowBuffer for Arc<Mutex<VecDeque<T>>> {
+        type Item = T;
+
+        fn push(&self, input: T) {
+            lock(self).push_back(input)
+        }
+        fn pop(&self) -> Option<T> {
+            lock(self).pop_front()
+        }
+        fn peek_front(&self) -> Option<T>
+        where
+            T: Clone,
+        {
+            lock(self).front().cloned()
+        }
+        fn len(&self) -> usize {
+            lock(self).len()
+        }
+        fn reserve(&self, additional: usize) {
+            lock(self).reserve(additional)
+        }
+        fn clear(&self) {
+            lock(self).clear()
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl<T> FusedBuffer for Arc<Mutex<VecDeque<T>>> {}
+
+    #[cfg(feature = "std")]
+    impl<T> ForkBuffer for Arc<Mutex<VecDeque<T>>> {
+        fn fork(&self) -> Self
+        where
+            T: Clone,
+        {
+            Arc::new(Mutex::new(lock(self).clone()))
+        }
+    }
+
+    /// A [`CellBuffer`] behind an `Rc`, for sharing a queue that isn't
+    /// `Clone` without having to keep the buffer alive separately: cloning
+    /// this gives another handle to the same queue, so it can be moved into
+    /// the iterators using it rather than borrowed by them.
+    pub struct SharedBuffer<Q>(Rc<CellBuffer<Q>>);
+
+    impl<Q> SharedBuffer<Q> {
+        pub fn with_queue(queue: Q) -> Self {
+            Self(Rc::new(CellBuffer::with_queue(queue)))
+        }
+
+        /// Takes the queue back out of the buffer, if this is the last
+        /// handle to it.
+        pub fn into_inner(self) -> Result<Q, Self> {
+            Rc::try_unwrap(self.0)
+                .map(CellBuffer::into_inner)
+                .map_err(Self)
+        }
+    }
+
+    impl<Q: Default> Default for SharedBuffer<Q> {
+        fn default() -> Self {
+            Self::with_queue(Q::default())
+        }
+    }
+
+    impl<Q> Clone for SharedBuffer<Q> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone())
+        }
+    }
+
+    impl<Q: QueueBackend> ControlFlowBuffer for SharedBuffer<Q> {
+        type Item = Q::Item;
+
+        fn push(&self, input: Q::Item) {
+            (&*self.0).push(input)
+        }
+        fn pop(&self) -> Option<Q::Item> {
+            (&*self.0).pop()
+        }
+        fn peek_front(&self) -> Option<Q::Item>
+        where
+            Q::Item: Clone,
+        {
+            (&*self.0).peek_front()
+        }
+        fn len(&self) -> usize {
+            (&*self.0).len()
+        }
+        fn reserve(&self, additional: usize) {
+            (&*self.0).reserve(additional)
+        }
+        fn clear(&self) {
+            (&*self.0).clear()
+        }
+    }
+
+    impl<Q: QueueBackend> FusedBuffer for SharedBuffer<Q> {}
+
+    /// A single-threaded buffer like [`Buffer`], but without the runtime
+    /// borrow checks `RefCell` does on every push and pop. It doesn't keep
+    /// count of divergent values, and can't be forked.
+    ///
+    /// This relies on nothing else being able to touch the queue while one
+    /// of its methods is using it, which is what makes it opt-in.
+    pub struct UnsafeBuffer<T> {
+        queue: Rc<UnsafeCell<VecDeque<T>>>,
+    }
+
+    // SAFETY: every use of the queue goes through `queue`, and is over
+    // before the method using it returns. The `Rc` makes this neither `Send`
+    // nor `Sync`, so those uses can't overlap across threads. They can't
+    // overlap on one thread either: while the reference is alive, none of
+    // them run any code other than `VecDeque`'s own. In particular they
+    // never clone or drop an item, which could run the caller's code, and
+    // that code could hold another handle to this buffer.
+    impl<T> UnsafeBuffer<T> {
+        /// # Safety
+        ///
+        /// The reference must not outlive the caller, and nothing that might
+        /// use this buffer (including cloning or dropping an item) may be
+        /// run while it's alive.
+        #[allow(clippy::mut_from_ref)]
+        unsafe fn queue(&self) -> &mut VecDeque<T> {
+            &mut *self.queue.get()
+        }
+    }
+
+    impl<T> Default for UnsafeBuffer<T> {
+        fn default() -> Self {
+            Self {
+                queue: Rc::default(),
+            }
+        }
+    }
+
+    impl<T> Clone for UnsafeBuffer<T> {
+        fn clone(&self) -> Self {
+            Self {
+                queue: self.queue.clone(),
+            }
+        }
+    }
+
+    impl<T> fmt::Debug for UnsafeBuffer<T> {
+        // the items' Debug is the caller's code, so this only shows the
+        // length.
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("UnsafeBuffer")
+                .field("len", &self.len())
+                .finish()
+        }
+    }
+
+    impl<T> ControlFlowBuffer for UnsafeBuffer<T> {
+        type Item = T;
+
+        #[inline]
+        fn push(&self, input: T) {
+            unsafe { self.queue() }.push_back(input)
+        }
+        #[inline]
+        fn pop(&self) -> Option<T> {
+            unsafe { self.queue() }.pop_front()
+        }
+        fn peek_front(&self) -> Option<T>
+        where
+            T: Clone,
+        {
+            // the item is taken out to be cloned, so the clone can't see it
+            // borrowed, and put back again afterwards even if it panics.
+            struct PutBack<'a, T>(&'a UnsafeBuffer<T>, Option<T>);
+            impl<'a, T> Drop for PutBack<'a, T> {
+                fn drop(&mut self) {
+                    if let Some(item) = self.1.take() {
+                        unsafe { self.0.queue() }.push_front(item)
+                    }
+                }
+            }
+
+            let front = PutBack(self, unsafe { self.queue() }.pop_front());
+            front.1.clone()
+        }
+        #[inline]
+        fn len(&self) -> usize {
+            unsafe { self.queue() }.len()
+        }
+        fn reserve(&self, additional: usize) {
+            unsafe { self.queue() }.reserve(additional)
+        }
+        fn clear(&self) {
+            // dropped only once the queue is no longer borrowed.
+            drop(core::mem::take(unsafe { self.queue() }))
+        }
+    }
+
+    impl<T> FusedBuffer for UnsafeBuffer<T> {}
+}
+
+#[cfg(feature = "alloc")]
+pub use is_alloc::*;
+
+#[cfg(feature = "heapless")]
+mod is_heapless {
+    use super::*;
+
+    use heapless::Deque;
+
+    /// A fixed-capacity buffer backed by a [`heapless::Deque`].
+    ///
+    /// As with [`ArrayBuffer`], [`ControlFlowBuffer`] is implemented for
+    /// `&HeaplessBuffer` and `N` must be at least as large as the longest
+    /// run of divergent values the source can produce.
+    ///
+    /// It can't be handed over by value (as in
+    /// `with_filtered_buf(HeaplessBuffer::<_, 8>::default(), ...)`), because
+    /// the filtering and recombining iterators each need a handle to the same
+    /// queue, and without an allocator to put it behind an `Rc` the only way
+    /// to share it is by reference. Cloning a by-value buffer would copy the
+    /// deque, and the divergent values pushed into one copy would never come
+    /// out of the other.
+    ///
+    /// Pushing into a full buffer panics, in release builds as well as debug
+    /// ones. The only thing a full deque could do otherwise is drop the
+    /// divergent value, and silently losing an error is worse than stopping.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// # use iteritor::IterFiltered;
+    /// # use iteritor::buffer::HeaplessBuffer;
+    /// let items = [Ok(1), Err("boom"), Ok(3)];
+    /// let buf = HeaplessBuffer::<_, 8>::default();
+    /// let doubled: Vec<_> = items
+    ///     .into_iter()
+    ///     .with_filtered_buf(&buf, |i| i.map(|n| n * 2))
+    ///     .collect();
+    ///
+    /// assert_eq!(doubled, [Ok(2), Err("boom"), Ok(6)]);
+    /// ```
+    pub type HeaplessBuffer<T, const N: usize> = CellBuffer<Deque<T, N>>;
+
+    impl<T, const N: usize> CellBuffer<Deque<T, N>> {
+        pub const fn new() -> Self {
+            Self::with_queue(Deque::new())
+        }
+    }
+
+    impl<T, const N: usize> QueueBackend for Deque<T, N> {
+        type Item = T;
+
+        fn push_back(&mut self, input: T) -> Result<(), T> {
+            Deque::push_back(self, input)
+        }
+        fn pop_front(&mut self) -> Option<T> {
+            Deque::pop_front(self)
+        }
+        fn front(&self) -> Option<&T> {
+            Deque::front(self)
+        }
+        fn len(&self) -> usize {
+            Deque::len(self)
+        }
+        fn clear(&mut self) {
+            Deque::clear(self)
+        }
+        fn capacity(&self) -> Option<usize> {
+            Some(N)
+        }
+    }
+}
+
+#[cfg(feature = "heapless")]
+pub use is_heapless::*;
+
+#[cfg(feature = "smallvec")]
+mod is_smallvec {
+    use super::*;
+
+    use smallvec::SmallVec;
+
+    /// A buffer that keeps up to `N` items inline and only allocates once a
+    /// run of divergent values gets longer than that.
+    ///
+    /// [`ControlFlowBuffer`] is implemented for `&SmallBuffer`. Popping
+    /// removes from the front of the vector, which is O(n) in the number of
+    /// buffered items, so this is only a win when runs are short.
+    pub type SmallBuffer<T, const N: usize> = CellBuffer<SmallVec<[T; N]>>;
+
+    impl<T, const N: usize> CellBuffer<SmallVec<[T; N]>> {
+        pub fn new() -> Self {
+            Self::with_queue(SmallVec::new())
+        }
+    }
+
+    impl<T, const N: usize> QueueBackend for SmallVec<[T; N]> {
+        type Item = T;
+
+        fn push_back(&mut self, input: T) -> Result<(), T> {
+            self.push(input);
+            Ok(())
+        }
+        fn pop_front(&mut self) -> Option<T> {
+            if self.is_empty() {
+                None
+            } else {
+                Some(self.remove(0))
+            }
+        }
+        fn front(&self) -> Option<&T> {
+            self.first()
+        }
+        fn len(&self) -> usize {
+            SmallVec::len(self)
+        }
+        fn reserve(&mut self, additional: usize) {
+            SmallVec::reserve(self, additional)
+        }
+        fn clear(&mut self) {
+            SmallVec::clear(self)
+        }
+    }
+}
+
+#[cfg(feature = "smallvec")]
+pub use is_smallvec::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filtered::tests::result_samples;
+    use crate::IterFiltered;
+
+    #[test]
+    fn array_buffer_recombines_in_order() {
+        let buf = ArrayBuffer::<_, 4>::new();
+        let mut output = result_samples().with_filtered_buf(&buf, |i| i.filter(|n| *n > 2));
+
+        assert_eq!(output.next(), Some(Err("boom")));
+        assert_eq!(output.next(), Some(Err("hi")));
+        assert_eq!(output.next(), Some(Ok(3)));
+        assert_eq!(output.next(), Some(Err("zoop")));
+        assert_eq!(output.next(), Some(Ok(5)));
+        assert_eq!(output.next(), None);
+    }
+
+    // Only uses what the `alloc` feature provides, so this also runs under
+    // `--no-default-features --features alloc`.
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn alloc_buffer_recombines_in_order() {
+        let buf = Buffer::default();
+        let mut output = result_samples().with_filtered_buf(buf.clone(), |i| i.filter(|n| *n > 2));
+
+        assert_eq!(output.next(), Some(Err("boom")));
+        assert_eq!(output.next(), Some(Err("hi")));
+        assert_eq!(output.next(), Some(Ok(3)));
+        assert_eq!(buf.len(), 0);
+        assert_eq!(output.next(), Some(Err("zoop")));
+        assert_eq!(output.next(), Some(Ok(5)));
+        assert_eq!(output.next(), None);
+
+        let mut batch = alloc::vec::Vec::new();
+        let mut source = result_samples();
+        assert_eq!(buf.next_unwrapped_batch(&mut source, &mut batch, 4), 4);
+        assert_eq!(batch, [1, 2, 1, 3]);
+        assert_eq!(buf.len(), 2);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn array_buffer_matches_std_buffer() {
+        let buf = ArrayBuffer::<_, 4>::new();
+        let with_array: Vec<_> = result_samples()
+            .with_filtered_buf(&buf, |i| i.map(|n| n * 2))
+            .collect();
+        let with_std: Vec<_> = result_samples()
+            .with_filtered(|i| i.map(|n| n * 2))
+            .collect();
+
+        assert_eq!(with_array, with_std);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn unsafe_buffer_matches_std_buffer() {
+        let with_unsafe: Vec<_> = result_samples()
+            .with_filtered_buf(UnsafeBuffer::default(), |i| i.flat_map(|n| [n, n * 10]))
+            .collect();
+        let with_std: Vec<_> = result_samples()
+            .with_filtered(|i| i.flat_map(|n| [n, n * 10]))
+            .collect();
+        assert_eq!(with_unsafe, with_std);
+
+        let with_unsafe: Vec<_> = result_samples()
+            .with_filtered_buf(UnsafeBuffer::default(), |i| i.filter(|n| *n > 2).rev())
+            .collect();
+        let with_std: Vec<_> = result_samples()
+            .with_filtered(|i| i.filter(|n| *n > 2).rev())
+            .collect();
+        assert_eq!(with_unsafe, with_std);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn unsafe_buffer_operations() {
+        let buf = UnsafeBuffer::default();
+        buf.push(Err::<u32, _>("boom"));
+        buf.push(Err("hi"));
+
+        assert_eq!(buf.peek_front(), Some(Err("boom")));
+        assert_eq!(buf.len(), 2);
+        assert_eq!(buf.clone().pop(), Some(Err("boom")));
+        assert_eq!(buf.len(), 1);
+        buf.clear();
+        assert!(buf.is_empty());
+        assert_eq!(buf.pop(), None);
+        assert_eq!(buf.peek_front(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn next_unwrapped_batch_fills_batches() {
+        let buf = Buffer::default();
+        let mut source = result_samples();
+        let mut out = Vec::new();
+
+        assert_eq!(buf.next_unwrapped_batch(&mut source, &mut out, 4), 4);
+        assert_eq!(out, [1, 2, 1, 3]);
+        assert_eq!(buf.drain().collect::<Vec<_>>(), [Err("boom"), Err("hi")]);
+
+        assert_eq!(buf.next_unwrapped_batch(&mut source, &mut out, 4), 2);
+        assert_eq!(out, [1, 2, 1, 3, 1, 5]);
+        assert_eq!(buf.drain().collect::<Vec<_>>(), [Err("zoop")]);
+
+        assert_eq!(buf.next_unwrapped_batch(&mut source, &mut out, 4), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn clear_empties_buffer() {
+        let buf = Buffer::default();
+        buf.push(Err::<u32, _>("boom"));
+        buf.push(Err("hi"));
+        buf.push(Ok(3));
+        buf.clear();
+        assert_eq!(buf.pop(), None);
+
+        let buf = ArrayBuffer::<_, 4>::new();
+        (&buf).push(1);
+        (&buf).push(2);
+        (&buf).clear();
+        assert_eq!((&buf).pop(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn bounded_buffer_limits_long_error_runs() {
+        let buf = BoundedBuffer::new(3, Err(-1));
+        let mut samples = (0..10).map(Err).chain([Ok("done")]);
+        let mut output = samples
+            .by_ref()
+            .with_filtered_buf(buf.clone(), |i| i.map(str::trim));
+
+        assert_eq!(output.next(), Some(Err(0)));
+        // the filtering side stopped as soon as the buffer filled up, with
+        // the sentinel queued behind what it had.
+        assert_eq!(buf.len(), 3);
+        assert!(buf.is_saturated());
+
+        let rest: Vec<_> = output.collect();
+        assert_eq!(rest, [Err(1), Err(2), Err(-1)]);
+        // and the rest of the source wasn't touched.
+        assert_eq!(samples.next(), Some(Err(3)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn bounded_buffer_saturates_at_cap() {
+        let buf = BoundedBuffer::new(2, Err("full"));
+        let mut samples = [Err("a"), Err("b"), Err("c"), Ok(1)].into_iter();
+
+        assert_eq!(buf.next_unwrapped(&mut samples), None);
+        assert!(buf.is_saturated());
+        assert_eq!(
+            buf.drain().collect::<Vec<_>>(),
+            [Err("a"), Err("b"), Err("full")]
+        );
+        assert_eq!(samples.next(), Some(Err("c")));
+
+        // it stays saturated once drained.
+        assert!(buf.is_saturated());
+        assert_eq!(buf.next_unwrapped(&mut samples), None);
+        assert_eq!(samples.next(), Some(Ok(1)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn bounded_buffer_overflow_shows_through_fusing_chains() {
+        let samples = [Err(0), Err(1), Err(2), Ok(3), Ok(4), Err(5), Ok(6)];
+        let output: Vec<_> = samples
+            .into_iter()
+            .with_filtered_buf(BoundedBuffer::new(2, Err(-1)), |i| i.flat_map(|n| [n]))
+            .collect();
+        assert_eq!(output, [Err(0), Err(1), Err(-1)]);
+
+        // runs shorter than the cap never see the sentinel.
+        let output: Vec<_> = samples
+            .into_iter()
+            .with_filtered_buf(BoundedBuffer::new(4, Err(-1)), |i| i.flat_map(|n| [n]))
+            .collect();
+        assert_eq!(output, samples);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn buffer_debug_shows_pending_items() {
+        let buf = Buffer::<Result<u32, _>>::default();
+        buf.push(Err("boom"));
+        buf.push(Err("hi"));
+
+        assert_eq!(format!("{:?}", buf), r#"Buffer([Err("boom"), Err("hi")])"#);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn borrowed_refcell_buffer() {
+        use core::cell::RefCell;
+        use std::collections::VecDeque;
+
+        let cell = RefCell::new(VecDeque::new());
+        let output: Vec<_> = result_samples()
+            .with_filtered_buf(&cell, |i| i.filter(|n| *n > 2))
+            .collect();
+
+        assert_eq!(output, [Err("boom"), Err("hi"), Ok(3), Err("zoop"), Ok(5)]);
+        assert!(cell.borrow().is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn drain_empties_buffer_in_order() {
+        let buf = Buffer::<Result<u32, _>>::default();
+        buf.push(Err("a"));
+        buf.push(Err("b"));
+        buf.push(Err("c"));
+
+        assert_eq!(
+            buf.drain().collect::<Vec<_>>(),
+            [Err("a"), Err("b"), Err("c")]
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn peek_front_does_not_consume() {
+        let buf = Buffer::<Result<u32, _>>::default();
+        assert_eq!(buf.peek_front(), None);
+        buf.push(Err("x"));
+
+        assert_eq!(buf.peek_front(), Some(Err("x")));
+        assert_eq!(buf.peek_front(), Some(Err("x")));
+        assert_eq!(buf.pop(), Some(Err("x")));
+        assert_eq!(buf.peek_front(), None);
+    }
+
+    #[test]
+    fn fixed_buffers_peek_front() {
+        let array = ArrayBuffer::<_, 2>::new();
+        let ring = RingBuffer::<_, 2>::new();
+        let slot = SlotBuffer::new();
+        (&array).push(1);
+        (&ring).push(1);
+        (&slot).push(1);
+
+        assert_eq!((&array).peek_front(), Some(1));
+        assert_eq!((&ring).peek_front(), Some(1));
+        assert_eq!((&slot).peek_front(), Some(1));
+        assert_eq!((&array).pop(), Some(1));
+        assert_eq!((&ring).pop(), Some(1));
+        assert_eq!((&slot).pop(), Some(1));
+    }
+
+    #[cfg(all(feature = "std", feature = "serde"))]
+    #[test]
+    fn buffer_serde_round_trip() {
+        let buf: Buffer<Result<u32, String>> = Buffer::default();
+        buf.push(Err("a".to_string()));
+        buf.push(Err("b".to_string()));
+
+        let mut json = Vec::new();
+        serialize_buffer(&buf, &mut serde_json::Serializer::new(&mut json)).unwrap();
+        assert_eq!(json, br#"[{"Err":"a"},{"Err":"b"}]"#);
+
+        let restored: Buffer<Result<u32, String>> =
+            deserialize_buffer(&mut serde_json::Deserializer::from_slice(&json)).unwrap();
+        assert_eq!(restored.pop(), Some(Err("a".to_string())));
+        assert_eq!(restored.pop(), Some(Err("b".to_string())));
+        assert_eq!(restored.pop(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn sync_buffer_can_move_to_another_thread() {
+        let output =
+            result_samples().with_filtered_buf(SyncBuffer::default(), |i| i.filter(|n| *n > 2));
+        let collected = std::thread::spawn(move || output.collect::<Vec<_>>())
+            .join()
+            .unwrap();
+
+        assert_eq!(
+            collected,
+            [Err("boom"), Err("hi"), Ok(3), Err("zoop"), Ok(5)]
+        );
+    }
+
+    #[test]
+    fn slot_buffer_handles_isolated_divergents() {
+        let buf = SlotBuffer::new();
+        let mut output = result_samples()
+            .filter(|r| *r != Err("hi"))
+            .with_filtered_buf(&buf, |i| i.map(|n| n * 2));
+
+        assert_eq!(output.next(), Some(Ok(2)));
+        assert_eq!(output.next(), Some(Ok(4)));
+        assert_eq!(output.next(), Some(Ok(2)));
+        assert_eq!(output.next(), Some(Err("boom")));
+        assert_eq!(output.next(), Some(Ok(6)));
+        assert_eq!(output.next(), Some(Ok(2)));
+        assert_eq!(output.next(), Some(Err("zoop")));
+        assert_eq!(output.next(), Some(Ok(10)));
+        assert_eq!(output.next(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "consecutive divergents not supported")]
+    fn slot_buffer_panics_on_consecutive_divergents() {
+        let buf = SlotBuffer::new();
+        result_samples()
+            .with_filtered_buf(&buf, |i| i.map(|n| n * 2))
+            .for_each(drop);
+    }
+
+    #[test]
+    fn ring_buffer_overwrites_oldest() {
+        let buf = RingBuffer::<_, 1>::new();
+        let mut output = result_samples().with_filtered_buf(&buf, |i| i.filter(|n| *n > 2));
+
+        // "boom" gets pushed out by "hi" before 3 comes through.
+        assert_eq!(output.next(), Some(Err("hi")));
+        assert_eq!(output.next(), Some(Ok(3)));
+        assert_eq!(output.next(), Some(Err("zoop")));
+        assert_eq!(output.next(), Some(Ok(5)));
+        assert_eq!(output.next(), None);
+        assert_eq!(buf.dropped_count(), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn ring_buffer_drops_remaining_items() {
+        use std::rc::Rc;
+
+        let item = Rc::new(());
+        {
+            let buf = RingBuffer::<_, 2>::new();
+            (&buf).push(item.clone());
+            (&buf).push(item.clone());
+            (&buf).push(item.clone());
+            assert_eq!(buf.dropped_count(), 1);
+            assert_eq!(Rc::strong_count(&item), 3);
+        }
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
+
+    #[cfg(all(feature = "std", feature = "smallvec"))]
+    #[test]
+    fn small_buffer_matches_std_buffer() {
+        // a single inline slot forces the "boom", "hi" run to spill.
+        let buf = SmallBuffer::<_, 1>::new();
+        let with_small: Vec<_> = result_samples()
+            .with_filtered_buf(&buf, |i| i.filter(|n| *n > 1))
+            .collect();
+        let with_std: Vec<_> = result_samples()
+            .with_filtered(|i| i.filter(|n| *n > 1))
+            .collect();
+
+        assert_eq!(with_small, with_std);
+    }
+
+    #[test]
+    fn array_buffer_try_push_when_full() {
+        let buf = ArrayBuffer::<_, 2>::new();
+        assert_eq!(buf.try_push(1), Ok(()));
+        assert_eq!(buf.try_push(2), Ok(()));
+        assert_eq!(buf.try_push(3), Err(3));
+        assert_eq!((&buf).pop(), Some(1));
+        assert_eq!(buf.try_push(3), Ok(()));
+        assert_eq!((&buf).pop(), Some(2));
+        assert_eq!((&buf).pop(), Some(3));
+        assert_eq!((&buf).pop(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ArrayBuffer overflowed")]
+    fn array_buffer_panics_on_overflow() {
+        let buf = ArrayBuffer::<Result<u32, &str>, 1>::new();
+        result_samples()
+            .with_filtered_buf(&buf, |i| i.filter(|n| *n > 2))
+            .for_each(drop);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn buffer_usable_after_inner_panic() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let buf = Buffer::default();
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            result_samples()
+                .with_filtered_buf(buf.clone(), |i| {
+                    i.map(|n| {
+                        if n == 3 {
+                            panic!("inner chain panicked")
+                        } else {
+                            n
+                        }
+                    })
+                })
+                .for_each(drop)
+        }));
+
+        assert!(result.is_err());
+        assert!(buf.try_borrow_mut().is_ok());
+        assert_eq!(buf.drain().collect::<Vec<_>>(), [Err("boom"), Err("hi")]);
+        let output: Vec<_> = result_samples()
+            .with_filtered_buf(buf, |i| i.filter(|n| *n > 2))
+            .collect();
+        assert_eq!(output, [Err("boom"), Err("hi"), Ok(3), Err("zoop"), Ok(5)]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn peek_front_survives_panicking_clone() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        #[derive(Debug, PartialEq)]
+        struct NoClone(u32);
+        impl Clone for NoClone {
+            fn clone(&self) -> Self {
+                panic!("can't clone")
+            }
+        }
+
+        let array = ArrayBuffer::<_, 2>::new();
+        let slot = SlotBuffer::new();
+        (&array).push(NoClone(1));
+        (&slot).push(NoClone(2));
+
+        assert!(catch_unwind(AssertUnwindSafe(|| (&array).peek_front())).is_err());
+        assert!(catch_unwind(AssertUnwindSafe(|| (&slot).peek_front())).is_err());
+        assert_eq!((&array).pop(), Some(NoClone(1)));
+        assert_eq!((&slot).pop(), Some(NoClone(2)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn default_peek_front_keeps_the_order() {
+        use std::collections::VecDeque;
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        use std::rc::Rc;
+
+        // a buffer that only has the required methods, like one from
+        // outside the crate might.
+        struct Plain<T>(Rc<RefCell<VecDeque<T>>>);
+        impl<T> Clone for Plain<T> {
+            fn clone(&self) -> Self {
+                Self(self.0.clone())
+            }
+        }
+        impl<T> ControlFlowBuffer for Plain<T> {
+            type Item = T;
+            fn push(&self, input: T) {
+                self.0.borrow_mut().push_back(input)
+            }
+            fn pop(&self) -> Option<T> {
+                self.0.borrow_mut().pop_front()
+            }
+            fn len(&self) -> usize {
+                self.0.borrow().len()
+            }
+        }
+
+        let buf = Plain(Rc::default());
+        assert_eq!(buf.peek_front(), None::<u32>);
+        buf.extend_back([1, 2, 3]);
+        assert_eq!(buf.peek_front(), Some(1));
+        assert_eq!(buf.drain().collect::<Vec<_>>(), [1, 2, 3]);
+
+        #[derive(Debug, PartialEq)]
+        struct NoClone(u32);
+        impl Clone for NoClone {
+            fn clone(&self) -> Self {
+                panic!("can't clone")
+            }
+        }
+
+        let buf = Plain(Rc::default());
+        buf.extend_back([NoClone(1), NoClone(2)]);
+        assert!(catch_unwind(AssertUnwindSafe(|| buf.peek_front())).is_err());
+        assert_eq!(buf.drain().collect::<Vec<_>>(), [NoClone(1), NoClone(2)]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn next_wrapped_goes_through_push_and_pop() {
+        use core::cell::Cell;
+        use std::rc::Rc;
+
+        #[derive(Clone, Default)]
+        struct Counted(Buffer<Result<u32, &'static str>>, Rc<Cell<usize>>);
+        impl ControlFlowBuffer for Counted {
+            type Item = Result<u32, &'static str>;
+            fn push(&self, input: Self::Item) {
+                self.0.push(input)
+            }
+            fn pop(&self) -> Option<Self::Item> {
+                self.0.pop()
+            }
+            fn len(&self) -> usize {
+                self.0.len()
+            }
+            fn push_and_pop(&self, input: Self::Item) -> Self::Item {
+                self.1.set(self.1.get() + 1);
+                self.0.push_and_pop(input)
+            }
+        }
+
+        let buf = Counted::default();
+        let output: Vec<_> = result_samples()
+            .with_filtered_buf(buf.clone(), |i| i.filter(|n| *n > 2))
+            .collect();
+        assert_eq!(output, [Err("boom"), Err("hi"), Ok(3), Err("zoop"), Ok(5)]);
+        // once for each output of the chain.
+        assert_eq!(buf.1.get(), 2);
+    }
+
+    /// A deliberately naive backend, to check CellBuffer against another.
+    #[cfg(feature = "std")]
+    struct VecQueue<T>(Vec<T>);
+
+    #[cfg(feature = "std")]
+    impl<T> QueueBackend for VecQueue<T> {
+        type Item = T;
+
+        fn push_back(&mut self, input: T) -> Result<(), T> {
+            self.0.push(input);
+            Ok(())
+        }
+        fn pop_front(&mut self) -> Option<T> {
+            if self.0.is_empty() {
+                None
+            } else {
+                Some(self.0.remove(0))
+            }
+        }
+        fn front(&self) -> Option<&T> {
+            self.0.first()
+        }
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn cell_buffer_backends_agree() {
+        use std::collections::VecDeque;
+
+        let deque = CellBuffer::<VecDeque<_>>::default();
+        let vec = CellBuffer::with_queue(VecQueue(Vec::new()));
+        let with_deque: Vec<_> = result_samples()
+            .with_filtered_buf(&deque, |i| i.filter(|n| *n > 1))
+            .collect();
+        let with_vec: Vec<_> = result_samples()
+            .with_filtered_buf(&vec, |i| i.filter(|n| *n > 1))
+            .collect();
+
+        assert_eq!(with_deque, with_vec);
+        assert_eq!(
+            with_deque,
+            result_samples()
+                .with_filtered(|i| i.filter(|n| *n > 1))
+                .collect::<Vec<_>>()
+        );
+        assert!(deque.into_inner().is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn shared_buffer_without_clone() {
+        // VecQueue isn't Clone, and the buffer is moved into the returned
+        // iterator rather than borrowed.
+        fn pipeline(
+            buf: SharedBuffer<VecQueue<Result<u32, &'static str>>>,
+        ) -> impl Iterator<Item = Result<u32, &'static str>> {
+            result_samples().with_filtered_buf(buf, |i| i.filter(|n| *n > 1))
+        }
+
+        let buf = SharedBuffer::with_queue(VecQueue(Vec::new()));
+        let output: Vec<_> = pipeline(buf.clone()).collect();
+
+        assert_eq!(
+            output,
+            result_samples()
+                .with_filtered(|i| i.filter(|n| *n > 1))
+                .collect::<Vec<_>>()
+        );
+        assert!(buf.into_inner().ok().unwrap().0.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "CellBuffer overflowed: its queue can't hold")]
+    fn cell_buffer_panics_when_queue_is_full() {
+        struct OneSlot(Option<u32>);
+        impl QueueBackend for OneSlot {
+            type Item = u32;
+
+            fn push_back(&mut self, input: u32) -> Result<(), u32> {
+                match self.0 {
+                    Some(_) => Err(input),
+                    None => {
+                        self.0 = Some(input);
+                        Ok(())
+                    }
+                }
+            }
+            fn pop_front(&mut self) -> Option<u32> {
+                self.0.take()
+            }
+            fn front(&self) -> Option<&u32> {
+                self.0.as_ref()
+            }
+            fn len(&self) -> usize {
+                usize::from(self.0.is_some())
+            }
+        }
+
+        let buf = CellBuffer::with_queue(OneSlot(None));
+        (&buf).push(1);
+        (&buf).push(2);
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    #[should_panic(expected = "CellBuffer overflowed: more than 1 consecutive divergent values")]
+    fn heapless_buffer_panics_with_its_capacity() {
+        let buf = HeaplessBuffer::<_, 1>::new();
+        result_samples()
+            .with_filtered_buf(&buf, |i| i.filter(|n| *n > 2))
+            .for_each(drop);
+    }
+}
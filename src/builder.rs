@@ -0,0 +1,120 @@
+/*
+ Copyright 2022 The Iteritor Authors
+
+ Licensed under the Apache License, Version 2.0 (the "License");
+ you may not use this file except in compliance with the License.
+ You may obtain a copy of the License at
+
+      https://www.apache.org/licenses/LICENSE-2.0
+
+ Unless required by applicable law or agreed to in writing, software
+ distributed under the License is distributed on an "AS IS" BASIS,
+ WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ See the License for the specific language governing permissions and
+ limitations under the License.
+ */
+
+//! A builder for filtering pipelines, for when there's more to set up than
+//! the `with_filtered_*` shortcuts cover. See
+//! [`crate::IterFiltered::filtered`].
+
+use crate::filtered::{DefilteredIter, FilteredIter};
+use crate::residual::{FromResidualMap, MapResidual, ResidualMap};
+use crate::try_polyfill::Try;
+use crate::ControlFlowBuffer;
+
+/// Sets up a filtering pipeline a piece at a time, then runs a chain over
+/// it with [`FilteredBuilder::run`]. Each setting replaces the last one of
+/// the same kind, except for [`FilteredBuilder::map_residual`], which
+/// stacks.
+#[derive(Clone, Debug)]
+pub struct FilteredBuilder<Input, Buffer = DefaultBuffer, Map = FromResidualMap> {
+    input_iter: Input,
+    buffer: Buffer,
+    map: Map,
+}
+
+/// Stands in for a [`crate::buffer::Buffer`] of whatever type ends up being
+/// buffered, until a [`FilteredBuilder`] is run.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultBuffer;
+
+/// Buffers a [`FilteredBuilder`] can be run with.
+pub trait BuilderBuffer<Item> {
+    type Buffer: ControlFlowBuffer<Item = Item>;
+
+    fn into_buffer(self) -> Self::Buffer;
+}
+
+#[cfg(feature = "alloc")]
+impl<Item> BuilderBuffer<Item> for DefaultBuffer {
+    type Buffer = crate::buffer::Buffer<Item>;
+
+    fn into_buffer(self) -> Self::Buffer {
+        crate::buffer::Buffer::default()
+    }
+}
+
+impl<B: ControlFlowBuffer> BuilderBuffer<B::Item> for B {
+    type Buffer = B;
+
+    fn into_buffer(self) -> B {
+        self
+    }
+}
+
+impl<I> FilteredBuilder<I> {
+    pub fn new(input_iter: I) -> Self {
+        Self {
+            input_iter,
+            buffer: DefaultBuffer,
+            map: FromResidualMap,
+        }
+    }
+}
+
+impl<I, B, M> FilteredBuilder<I, B, M> {
+    /// Buffers divergent values in `buffer`, rather than a fresh
+    /// [`crate::buffer::Buffer`].
+    pub fn buffer<B2>(self, buffer: B2) -> FilteredBuilder<I, B2, M> {
+        FilteredBuilder {
+            input_iter: self.input_iter,
+            buffer,
+            map: self.map,
+        }
+    }
+
+    /// Gives out each divergent value ahead of the first output the chain
+    /// yields after pulling it. This is the only ordering the recombined
+    /// iterator has, so it's also what you get without asking; see
+    /// [`DefilteredIter`] for the details.
+    pub fn errors_first(self) -> Self {
+        self
+    }
+
+    /// Passes each divergent value through `f` before it's buffered, as
+    /// with [`FilteredIter::map_residual`].
+    pub fn map_residual<F, Y>(self, f: F) -> FilteredBuilder<I, B, MapResidual<M, F, Y>> {
+        FilteredBuilder {
+            input_iter: self.input_iter,
+            buffer: self.buffer,
+            map: MapResidual::new(self.map, f),
+        }
+    }
+
+    /// Calls `f` with an iterator over the normal values, and returns an
+    /// iterator recombining its output with the divergent values, as with
+    /// [`crate::IterFiltered::with_filtered_buf`].
+    pub fn run<O, F, T>(self, f: F) -> DefilteredIter<O, B::Buffer>
+    where
+        I: Iterator<Item = T>,
+        T: Try,
+        M: ResidualMap<T>,
+        M::Output: Try<Output = T::Output>,
+        B: BuilderBuffer<M::Output>,
+        F: FnMut(FilteredIter<I, B::Buffer, M>) -> O,
+        O: Iterator<Item = T::Output>,
+    {
+        crate::filter_with_map(self.input_iter, self.buffer.into_buffer(), self.map, f)
+    }
+}
@@ -14,12 +14,59 @@
  limitations under the License.
  */
 
-use crate::try_polyfill::Try;
-use crate::ControlFlowBuffer;
+use core::{convert::Infallible, fmt, iter::FusedIterator, ops::ControlFlow};
 
-pub struct FilteredIter<Input, Buffer> {
+#[cfg(feature = "alloc")]
+use alloc::{collections::VecDeque, vec::Vec};
+
+#[cfg(feature = "alloc")]
+use crate::buffer::CappedBuffer;
+use crate::residual::{
+    BranchIntact, FilterResidual, FromResidualMap, InspectResidual, IntactMap, MapResidual,
+    ResidualMap, Uninhabited,
+};
+use crate::try_polyfill::{FromResidual, Try};
+use crate::{ControlFlowBuffer, ForkBuffer, ForkScope, FusedBuffer};
+
+/// Iterator over the 'normal' values of its input, buffering any divergent
+/// values it comes across on the way.
+///
+/// This is double-ended when the input is. Divergent values are buffered in
+/// the order they're pulled from either end, so the recombined output
+/// follows that order too: reversing the chain inside `with_filtered` gives
+/// back exactly the reverse of the original sequence.
+///
+/// The chain given to [`crate::IterFiltered::with_filtered`] can be written
+/// as a function of its own, generic over the source and buffer. Bounding an
+/// item type on [`Try`] works too, but with the `nightly` feature that's
+/// the unstable `core::ops::Try`, so naming the item type keeps this
+/// working on both:
+///
+/// ```
+/// # use iteritor::IterFiltered;
+/// use iteritor::buffer::{ArrayBuffer, ControlFlowBuffer};
+/// use iteritor::filtered::FilteredIter;
+///
+/// fn evens_halved<I, B, E>(normal: FilteredIter<I, B>) -> impl Iterator<Item = u32>
+/// where
+///     I: Iterator<Item = Result<u32, E>>,
+///     B: ControlFlowBuffer<Item = Result<u32, E>>,
+/// {
+///     normal.filter(|n| n % 2 == 0).map(|n| n / 2)
+/// }
+///
+/// let buf = ArrayBuffer::<_, 2>::new();
+/// let output: Vec<Result<u32, &str>> = [Ok(2), Err("boom"), Ok(3), Ok(8)]
+///     .into_iter()
+///     .with_filtered_buf(&buf, evens_halved)
+///     .collect();
+///
+/// assert_eq!(output, [Ok(1), Err("boom"), Ok(4)]);
+/// ```
+pub struct FilteredIter<Input, Buffer, Map = FromResidualMap> {
     input_iter: Input,
     buffer: Buffer,
+    map: Map,
 }
 
 impl<I, B, T> FilteredIter<I, B>
@@ -27,19 +74,182 @@ where
     I: Iterator<Item = T>,
 {
     pub fn new(input_iter: I, buffer: B) -> Self {
-        Self { input_iter, buffer }
+        Self {
+            input_iter,
+            buffer,
+            map: FromResidualMap,
+        }
+    }
+
+    /// Buffers divergent values just as they came, rather than splitting
+    /// them into their residual and rebuilding them from it. For `Result`
+    /// and `Option` that makes no difference, but it avoids the round trip
+    /// for [`Try`] types whose `from_residual` does real work.
+    pub fn buffer_intact(self) -> FilteredIter<I, B, IntactMap>
+    where
+        T: BranchIntact,
+    {
+        FilteredIter::with_map(self.input_iter, self.buffer, IntactMap)
     }
 }
 
-impl<I, B, T> Iterator for FilteredIter<I, B>
+impl<F, B, T> FilteredIter<core::iter::FromFn<F>, B>
+where
+    F: FnMut() -> Option<T>,
+{
+    /// Creates a `FilteredIter` over the items returned by calling `f`
+    /// until it returns `None`, as with [`core::iter::from_fn`].
+    pub fn from_fn(f: F, buffer: B) -> Self {
+        Self::new(core::iter::from_fn(f), buffer)
+    }
+}
+
+impl<I, B, M> FilteredIter<I, B, M> {
+    pub(crate) fn with_map(input_iter: I, buffer: B, map: M) -> Self {
+        Self {
+            input_iter,
+            buffer,
+            map,
+        }
+    }
+
+    /// Gives back the source iterator, with whatever items it has left, and
+    /// the buffer, with whatever divergent values are still in it.
+    pub fn into_inner(self) -> (I, B) {
+        (self.input_iter, self.buffer)
+    }
+
+    /// The buffer divergent values are being put into.
+    pub fn buffer(&self) -> &B {
+        &self.buffer
+    }
+}
+
+impl<I, B, M, T> FilteredIter<I, B, M>
 where
     I: Iterator<Item = T>,
     T: Try,
-    B: ControlFlowBuffer<Item = T>,
+    M: ResidualMap<T>,
+{
+    /// Passes each divergent value through `f` before it's buffered. This
+    /// doesn't change the normal values coming out of this iterator, only
+    /// what gets recombined with them later, and can change its type as
+    /// long as the buffer holds the new one.
+    pub fn map_residual<F, Y>(self, f: F) -> FilteredIter<I, B, MapResidual<M, F, Y>>
+    where
+        F: FnMut(M::Output) -> Y,
+    {
+        FilteredIter {
+            input_iter: self.input_iter,
+            buffer: self.buffer,
+            map: MapResidual::new(self.map, f),
+        }
+    }
+
+    /// Calls `f` with each divergent value's residual as it's found, before
+    /// it's buffered. Good for logging or counting errors without changing
+    /// anything about the output.
+    pub fn inspect_residual<F>(self, f: F) -> FilteredIter<I, B, InspectResidual<M, F>>
+    where
+        F: FnMut(&T::Residual),
+    {
+        FilteredIter {
+            input_iter: self.input_iter,
+            buffer: self.buffer,
+            map: InspectResidual::new(self.map, f),
+        }
+    }
+
+    /// Only buffers the divergent values `predicate` returns true for. The
+    /// rest are dropped on the spot and never show up in the recombined
+    /// output.
+    pub fn filter_residual<P>(self, predicate: P) -> FilteredIter<I, B, FilterResidual<M, P>>
+    where
+        P: FnMut(&M::Output) -> bool,
+    {
+        FilteredIter {
+            input_iter: self.input_iter,
+            buffer: self.buffer,
+            map: FilterResidual::new(self.map, predicate),
+        }
+    }
+
+    /// Maps each normal value with `f`, which can fail. An `Err` from it
+    /// becomes a new divergent value, converted into the buffer's item type
+    /// the way `?` would and buffered right alongside the ones from the
+    /// source, so both kinds come out of the recombined iterator in order.
+    pub fn try_map<F, U, E>(self, f: F) -> TryMap<I, B, M, F>
+    where
+        F: FnMut(T::Output) -> Result<U, E>,
+    {
+        TryMap { inner: self, f }
+    }
+
+    /// Makes this peekable, like [`Iterator::peekable`]. Peeking pulls from
+    /// the source just as `next` would, so any divergent values it passes
+    /// are buffered then and there, and come out of the recombined iterator
+    /// ahead of the peeked value (or whatever the chain makes of it).
+    pub fn peekable(self) -> PeekableFiltered<I, B, M> {
+        PeekableFiltered {
+            inner: self,
+            peeked: None,
+        }
+    }
+
+    /// Yields overlapping windows of `size` consecutive normal values, like
+    /// [`slice::windows`], as [`Item::Value`]s. This takes over the buffer
+    /// rather than leaving it to be recombined: each divergent value comes
+    /// out as an [`Item::Divergent`] where it fell between the normal
+    /// values, and starts the window over, so no window spans one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    ///
+    /// [`Item::Value`]: crate::tagged::Item::Value
+    /// [`Item::Divergent`]: crate::tagged::Item::Divergent
+    #[cfg(feature = "alloc")]
+    pub fn windows(self, size: usize) -> Windows<I, B, M> {
+        assert!(size != 0, "window size must be non-zero");
+        Windows {
+            inner: self,
+            size,
+            window: VecDeque::with_capacity(size),
+            pending: None,
+        }
+    }
+}
+
+/// Cloning a `FilteredIter` forks its buffer, so the two copies don't share
+/// buffered divergent values.
+impl<I, B, M> Clone for FilteredIter<I, B, M>
+where
+    I: Clone,
+    B: ForkBuffer,
+    B::Item: Clone,
+    M: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            input_iter: self.input_iter.clone(),
+            buffer: self.buffer.fork(),
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<I, B, M, T> Iterator for FilteredIter<I, B, M>
+where
+    I: Iterator<Item = T>,
+    T: Try,
+    B: ControlFlowBuffer,
+    M: ResidualMap<T, Output = B::Item>,
 {
     type Item = T::Output;
+    #[inline]
     fn next(&mut self) -> Option<T::Output> {
-        self.buffer.next_unwrapped(&mut self.input_iter)
+        self.buffer
+            .next_unwrapped_map(&mut self.input_iter, &mut self.map)
     }
     fn size_hint(&self) -> (usize, Option<usize>) {
         // since this is a filtering iterator, we may give out
@@ -49,10 +259,414 @@ where
     }
 }
 
+/// Divergent values found from the back go into the same queue as those
+/// found from the front, in the order they're found, so the buffer always
+/// holds items in the order they were pulled from the source.
+impl<I, B, M, T> DoubleEndedIterator for FilteredIter<I, B, M>
+where
+    I: DoubleEndedIterator<Item = T>,
+    T: Try,
+    B: ControlFlowBuffer,
+    M: ResidualMap<T, Output = B::Item>,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<T::Output> {
+        self.buffer
+            .next_unwrapped_map(&mut self.input_iter.by_ref().rev(), &mut self.map)
+    }
+}
+
+impl<I, B, M, T> FusedIterator for FilteredIter<I, B, M>
+where
+    I: FusedIterator<Item = T>,
+    T: Try,
+    B: FusedBuffer,
+    M: ResidualMap<T, Output = B::Item>,
+{
+}
+
+/// Iterator that maps the normal values of a [`FilteredIter`] with a
+/// function that can fail, buffering failures as divergent values. See
+/// [`FilteredIter::try_map`].
+pub struct TryMap<Input, Buffer, Map, F> {
+    inner: FilteredIter<Input, Buffer, Map>,
+    f: F,
+}
+
+impl<I, B, M, F, T, U, E> Iterator for TryMap<I, B, M, F>
+where
+    I: Iterator<Item = T>,
+    T: Try,
+    B: ControlFlowBuffer,
+    B::Item: FromResidual<Result<Infallible, E>>,
+    M: ResidualMap<T, Output = B::Item>,
+    F: FnMut(T::Output) -> Result<U, E>,
+{
+    type Item = U;
+    fn next(&mut self) -> Option<U> {
+        loop {
+            match (self.f)(self.inner.next()?) {
+                Ok(output) => return Some(output),
+                Err(err) => {
+                    let buffer = &self.inner.buffer;
+                    buffer.count_divergent();
+                    buffer.push(B::Item::from_residual(Err(err)));
+                }
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<I, B, M, F, T, U, E> FusedIterator for TryMap<I, B, M, F>
+where
+    I: FusedIterator<Item = T>,
+    T: Try,
+    B: FusedBuffer,
+    B::Item: FromResidual<Result<Infallible, E>>,
+    M: ResidualMap<T, Output = B::Item>,
+    F: FnMut(T::Output) -> Result<U, E>,
+{
+}
+
+/// A [`FilteredIter`] that can look at its next normal value without
+/// taking it. See [`FilteredIter::peekable`].
+pub struct PeekableFiltered<Input, Buffer, Map>
+where
+    Input: Iterator,
+    Input::Item: Try,
+{
+    inner: FilteredIter<Input, Buffer, Map>,
+    // `Some(None)` once a peek has found the input exhausted.
+    peeked: Option<Option<<Input::Item as Try>::Output>>,
+}
+
+impl<I, B, M, T> PeekableFiltered<I, B, M>
+where
+    I: Iterator<Item = T>,
+    T: Try,
+    B: ControlFlowBuffer,
+    M: ResidualMap<T, Output = B::Item>,
+{
+    /// The next normal value, without taking it.
+    pub fn peek(&mut self) -> Option<&T::Output> {
+        let inner = &mut self.inner;
+        self.peeked.get_or_insert_with(|| inner.next()).as_ref()
+    }
+
+    /// As [`PeekableFiltered::peek`], but the value can be changed before
+    /// it's taken.
+    pub fn peek_mut(&mut self) -> Option<&mut T::Output> {
+        let inner = &mut self.inner;
+        self.peeked.get_or_insert_with(|| inner.next()).as_mut()
+    }
+
+    /// Takes the next normal value only if `func` returns true for it.
+    pub fn next_if(&mut self, func: impl FnOnce(&T::Output) -> bool) -> Option<T::Output> {
+        match self.next() {
+            Some(next) if func(&next) => Some(next),
+            other => {
+                self.peeked = Some(other);
+                None
+            }
+        }
+    }
+}
+
+impl<I, B, M, T> Iterator for PeekableFiltered<I, B, M>
+where
+    I: Iterator<Item = T>,
+    T: Try,
+    B: ControlFlowBuffer,
+    M: ResidualMap<T, Output = B::Item>,
+{
+    type Item = T::Output;
+    #[inline]
+    fn next(&mut self) -> Option<T::Output> {
+        match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => self.inner.next(),
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (low, high) = self.inner.size_hint();
+        match self.peeked {
+            Some(None) => (0, Some(0)),
+            Some(Some(_)) => (
+                low.saturating_add(1),
+                high.and_then(|high| high.checked_add(1)),
+            ),
+            None => (low, high),
+        }
+    }
+}
+
+impl<I, B, M, T> FusedIterator for PeekableFiltered<I, B, M>
+where
+    I: FusedIterator<Item = T>,
+    T: Try,
+    B: FusedBuffer,
+    M: ResidualMap<T, Output = B::Item>,
+{
+}
+
+/// Iterator over windows of the normal values of a [`FilteredIter`], with
+/// the divergent values between them. See [`FilteredIter::windows`].
+#[cfg(feature = "alloc")]
+pub struct Windows<Input, Buffer, Map>
+where
+    Input: Iterator,
+    Input::Item: Try,
+{
+    inner: FilteredIter<Input, Buffer, Map>,
+    size: usize,
+    window: VecDeque<<Input::Item as Try>::Output>,
+    // a normal value that divergent values were found ahead of, held back
+    // until they've been given out.
+    pending: Option<<Input::Item as Try>::Output>,
+}
+
+#[cfg(feature = "alloc")]
+impl<I, B, M, T> Iterator for Windows<I, B, M>
+where
+    I: Iterator<Item = T>,
+    T: Try,
+    T::Output: Clone,
+    B: ControlFlowBuffer,
+    M: ResidualMap<T, Output = B::Item>,
+{
+    type Item = crate::tagged::Item<Vec<T::Output>, B::Item>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(divergent) = self.inner.buffer.pop() {
+                return Some(crate::tagged::Item::Divergent(divergent));
+            }
+            let next = match self.pending.take() {
+                Some(pending) => pending,
+                None => match self.inner.next() {
+                    Some(next) if self.inner.buffer.is_empty() => next,
+                    // divergent values were passed on the way to this one,
+                    // so they break up the window and come out first.
+                    Some(next) => {
+                        self.window.clear();
+                        self.pending = Some(next);
+                        continue;
+                    }
+                    None if self.inner.buffer.is_empty() => return None,
+                    None => {
+                        self.window.clear();
+                        continue;
+                    }
+                },
+            };
+            self.window.push_back(next);
+            if self.window.len() > self.size {
+                self.window.pop_front();
+            }
+            if self.window.len() == self.size {
+                let window = self.window.iter().cloned().collect();
+                return Some(crate::tagged::Item::Value(window));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I, B, M, T> FusedIterator for Windows<I, B, M>
+where
+    I: FusedIterator<Item = T>,
+    T: Try,
+    T::Output: Clone,
+    B: FusedBuffer,
+    M: ResidualMap<T, Output = B::Item>,
+{
+}
+
+/// Iterator that gives out a sentinel, and then nothing more, once its
+/// [`CappedBuffer`] overflows. See [`crate::IterFiltered::with_filtered_capped`].
+#[cfg(feature = "alloc")]
+pub struct CappedIter<Input, T> {
+    inner: Input,
+    buffer: CappedBuffer<T>,
+    sentinel: Option<T>,
+}
+
+#[cfg(feature = "alloc")]
+impl<I, T> CappedIter<I, T> {
+    pub fn new(inner: I, buffer: CappedBuffer<T>, sentinel: T) -> Self {
+        Self {
+            inner,
+            buffer,
+            sentinel: Some(sentinel),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I, T> Iterator for CappedIter<I, T>
+where
+    I: Iterator<Item = T>,
+{
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if !self.buffer.overflowed() {
+            // the overflow can happen while pulling this item, in which case
+            // it's dropped along with whatever else was buffered.
+            let next = self.inner.next();
+            if !self.buffer.overflowed() {
+                return next;
+            }
+        }
+        self.buffer.clear();
+        self.sentinel.take()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.buffer.overflowed() {
+            let left = self.sentinel.iter().count();
+            return (left, Some(left));
+        }
+        // the sentinel could stand in for any number of items, but can
+        // add one where there wasn't one.
+        let (_, high) = self.inner.size_hint();
+        (0, high.and_then(|high| high.checked_add(1)))
+    }
+}
+
+/// Iterator that unwraps the items of a source that can never diverge,
+/// because its residual is [`Uninhabited`]. Unlike [`FilteredIter`] it
+/// gives exactly one output per source item, so it keeps the source's
+/// length. See [`crate::IterFiltered::with_infallible`].
+#[derive(Clone, Debug)]
+pub struct InfallibleIter<Input>(Input);
+
+impl<I> InfallibleIter<I> {
+    pub fn new(input_iter: I) -> Self {
+        Self(input_iter)
+    }
+}
+
+fn unwrap_infallible<T>(item: T) -> T::Output
+where
+    T: Try,
+    T::Residual: Uninhabited,
+{
+    match item.branch() {
+        ControlFlow::Continue(output) => output,
+        ControlFlow::Break(residual) => residual.unreachable(),
+    }
+}
+
+impl<I, T> Iterator for InfallibleIter<I>
+where
+    I: Iterator<Item = T>,
+    T: Try,
+    T::Residual: Uninhabited,
+{
+    type Item = T::Output;
+    #[inline]
+    fn next(&mut self) -> Option<T::Output> {
+        self.0.next().map(unwrap_infallible)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+    fn fold<A, F>(self, init: A, mut f: F) -> A
+    where
+        F: FnMut(A, T::Output) -> A,
+    {
+        self.0
+            .fold(init, |acc, next| f(acc, unwrap_infallible(next)))
+    }
+}
+
+impl<I, T> DoubleEndedIterator for InfallibleIter<I>
+where
+    I: DoubleEndedIterator<Item = T>,
+    T: Try,
+    T::Residual: Uninhabited,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<T::Output> {
+        self.0.next_back().map(unwrap_infallible)
+    }
+}
+
+impl<I, T> ExactSizeIterator for InfallibleIter<I>
+where
+    I: ExactSizeIterator<Item = T>,
+    T: Try,
+    T::Residual: Uninhabited,
+{
+}
+
+impl<I, T> FusedIterator for InfallibleIter<I>
+where
+    I: FusedIterator<Item = T>,
+    T: Try,
+    T::Residual: Uninhabited,
+{
+}
+
+/// Iterator that recombines the output of an iterator chain over a
+/// [`FilteredIter`] with the divergent values it buffered.
+///
+/// The chain doesn't have to give one output per normal value it consumes.
+/// Each divergent value comes out just before the first output the chain
+/// yields after pulling it from the source, so outputs are never delayed
+/// waiting on divergent values and vice versa:
+///
+/// - A chain that yields several items per input (eg. `flat_map`) has all of
+///   them come out together, after any divergent values pulled along with
+///   the input that produced them.
+/// - A chain that yields fewer (eg. `filter`, `skip`, `skip_while` or
+///   `step_by`) has
+///   divergent values come out before the next item it does yield, or at the
+///   end if there isn't one. Where they were among the values it dropped
+///   makes no difference: with `step_by(2)`,
+///   `[Ok(1), Ok(2), Err(e), Ok(3), Ok(4)]` gives `[Ok(1), Err(e), Ok(3)]`,
+///   and so does `[Ok(1), Err(e), Ok(2), Ok(3), Ok(4)]`.
+/// - A chain that stops early (eg. `take` or `take_while`) only gives back
+///   the divergent values it pulled before stopping. Anything after that is
+///   never pulled from the source at all. `take_while` has to pull the value
+///   that fails its predicate to know to stop, so divergent values between
+///   its last output and that value come out at the end, after everything
+///   it yielded.
+/// - A chain that looks ahead before yielding (eg. `peekable`) will have
+///   divergent values it pulled while doing so come out before the output,
+///   even if that output came from an earlier normal value.
+/// - A chain that reorders its input (eg. by collecting and sorting it)
+///   gets no help: divergent values are placed by when they were pulled,
+///   not by where the output they end up next to came from. A chain that
+///   sorts everything pulls the whole source first, so all the divergent
+///   values come out ahead of all of its output.
+///
+/// So the output is only in source order if the chain keeps its own output
+/// in order and doesn't look ahead. Use
+/// [`crate::IterFiltered::with_filtered_ordered`] to have that checked.
+///
+/// This is double-ended when the chain is, and the two ends can be mixed
+/// freely: whatever is buffered by pulling from one end only comes out of
+/// that end, so taking from both gives the same items as consuming from
+/// only one would, in the same places. Items buffered by the back end are
+/// kept at one end of the queue and the rest at the other, so switching
+/// ends while both have items buffered walks the queue round to get at the
+/// other end's, and once the source runs out the other end's items have to
+/// be taken from the far end of theirs. Neither is fast, so prefer sticking
+/// to one end while lots of items are buffered. Anything pushed to the
+/// buffer from outside while the back end has items buffered is treated as
+/// the front end's, but may not come out in order.
 pub struct DefilteredIter<Input, Buffer> {
     input_iter: Input,
     buffer: Buffer,
     original_max: Option<usize>,
+    // whether the last call left the buffer empty.
+    drained: bool,
+    // how many of the buffered items were pulled by the back end, and
+    // whether they're at the front of the queue rather than the back.
+    back_len: usize,
+    back_first: bool,
 }
 
 impl<I, B, T> DefilteredIter<I, B>
@@ -66,36 +680,509 @@ where
             input_iter,
             buffer,
             original_max,
+            drained: false,
+            back_len: 0,
+            back_first: false,
         }
     }
+
+    /// How many divergent values have been found in the source so far, if
+    /// the buffer keeps count.
+    pub fn divergents_seen(&self) -> Option<usize> {
+        self.buffer.divergents_seen()
+    }
+
+    /// Gives up on the chain and gives back an iterator over the divergent
+    /// values still buffered, without pulling anything more from the source.
+    /// Any output of the chain that was queued up behind them is dropped.
+    pub fn replay_buffer(self) -> ReplayBuffer<B> {
+        ReplayBuffer(self.buffer)
+    }
 }
 
-impl<I, B, T> Iterator for DefilteredIter<I, B>
+#[cfg(feature = "alloc")]
+impl<I, B> DefilteredIter<I, B>
 where
-    I: Iterator<Item = T::Output>,
-    T: Try,
-    B: ControlFlowBuffer<Item = T>,
+    I: Iterator<Item = <B::Item as Try>::Output>,
+    B: ControlFlowBuffer,
+    B::Item: Try,
 {
-    type Item = T;
-    fn next(&mut self) -> Option<T> {
-        self.buffer.next_wrapped(&mut self.input_iter)
+    /// Runs another filtering pass over the output of this one, with a
+    /// fresh [`crate::buffer::Buffer`]. This is the same as calling
+    /// [`crate::IterFiltered::with_filtered`] on it, but reads better at the
+    /// end of a multi-stage pipeline.
+    pub fn refilter<O, F>(self, f: F) -> DefilteredIter<O, crate::buffer::Buffer<B::Item>>
+    where
+        F: FnMut(FilteredIter<Self, crate::buffer::Buffer<B::Item>>) -> O,
+        O: Iterator<Item = <B::Item as Try>::Output>,
+    {
+        crate::IterFiltered::with_filtered(self, f)
+    }
+
+    /// Collects the output into a `Vec`, stopping at the first divergent
+    /// value and returning that instead, as collecting into
+    /// `Result<Vec<_>, _>` would. The chain's output goes straight into the
+    /// `Vec`, without being wrapped up only to be taken apart again.
+    pub fn into_result_vec<R>(mut self) -> R
+    where
+        R: Try<Output = Vec<<B::Item as Try>::Output>> + FromResidual<<B::Item as Try>::Residual>,
+    {
+        let mut output = Vec::with_capacity(self.size_hint().0);
+        if self.back_len != 0 {
+            // the back end's items aren't in order in the queue, so leave
+            // putting them in place to `next`.
+            for next in self {
+                match next.branch() {
+                    ControlFlow::Continue(next) => output.push(next),
+                    ControlFlow::Break(residual) => return R::from_residual(residual),
+                }
+            }
+            return R::from_output(output);
+        }
+        loop {
+            let next = self.input_iter.next();
+            // anything buffered comes out before whatever was just pulled.
+            while let Some(buffered) = self.buffer.pop() {
+                match buffered.branch() {
+                    ControlFlow::Continue(buffered) => output.push(buffered),
+                    ControlFlow::Break(residual) => return R::from_residual(residual),
+                }
+            }
+            match next {
+                Some(next) => output.push(next),
+                None => return R::from_output(output),
+            }
+        }
+    }
+
+    /// Appends the output to `target` up to the first divergent value, and
+    /// returns that instead, as extending with a `Result` would. Unlike
+    /// [`DefilteredIter::into_result_vec`] this only takes the iterator by
+    /// reference, so anything after the divergent value is still there to
+    /// be pulled afterwards.
+    pub fn extend_until_err<R>(&mut self, target: &mut Vec<<B::Item as Try>::Output>) -> R
+    where
+        R: Try<Output = ()> + FromResidual<<B::Item as Try>::Residual>,
+    {
+        for next in self {
+            match next.branch() {
+                ControlFlow::Continue(output) => target.push(output),
+                ControlFlow::Break(residual) => return R::from_residual(residual),
+            }
+        }
+        R::from_output(())
+    }
+}
+
+/// Cloning gives an independent copy, with its own copy of anything already
+/// buffered, which the copy of the chain inside it feeds into.
+impl<I, B> Clone for DefilteredIter<I, B>
+where
+    I: Clone,
+    B: ForkScope,
+    B::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        let (buffer, input_iter) = self.buffer.fork_scope(|| self.input_iter.clone());
+        Self {
+            input_iter,
+            buffer,
+            original_max: self.original_max,
+            drained: self.drained,
+            back_len: self.back_len,
+            back_first: self.back_first,
+        }
+    }
+}
+
+impl<I, B: ControlFlowBuffer> fmt::Debug for DefilteredIter<I, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DefilteredIter")
+            .field("input_iter", &format_args!(".."))
+            .field("buffered", &self.buffer.len())
+            .field("original_max", &self.original_max)
+            .finish()
+    }
+}
+
+// these bounds are written in terms of B::Item rather than a separate type
+// parameter, which (as of rust 1.95) avoids an ICE on `by_ref()` chains.
+impl<I, B> Iterator for DefilteredIter<I, B>
+where
+    I: Iterator<Item = <B::Item as Try>::Output>,
+    B: ControlFlowBuffer,
+    B::Item: Try,
+{
+    type Item = B::Item;
+    #[inline]
+    fn next(&mut self) -> Option<B::Item> {
+        if self.back_len == 0 {
+            return self
+                .buffer
+                .next_wrapped_tracked(&mut self.input_iter, &mut self.drained);
+        }
+        self.drained = false;
+        if self.buffer.len() == self.back_len {
+            // nothing of the front end's is buffered, so anything the pull
+            // buffers goes in behind the back end's items.
+            match self.input_iter.next() {
+                Some(next) if self.buffer.len() == self.back_len => {
+                    return Some(B::Item::from_output(next));
+                }
+                Some(next) => self.buffer.push(B::Item::from_output(next)),
+                None if self.buffer.len() != self.back_len => {}
+                // the source has run out, so the last of the back end's
+                // items is the next one from the front.
+                None => {
+                    rotate(&self.buffer, self.back_len - 1);
+                    self.back_len -= 1;
+                    return self.buffer.pop();
+                }
+            }
+            self.back_first = true;
+        }
+        if self.back_first {
+            rotate(&self.buffer, self.back_len);
+            self.back_first = false;
+        }
+        self.buffer.pop()
     }
     fn size_hint(&self) -> (usize, Option<usize>) {
         // out max output size is the bigger of the original iterator's
         // maximum size (since we're adding things back in) or our input
         // iterator's max size (since it might be doing something like
-        // duping each output)
-        let max_size = [self.input_iter.size_hint().1, self.original_max]
-            .into_iter()
-            .flatten()
-            .max();
-        (0, max_size)
+        // duping each output). If either is unbounded, so are we: the chain
+        // could give out any number of items, or the source any number of
+        // divergent values. Anything already buffered is guaranteed to come
+        // out, on top of whatever the input is sure to give.
+        let (input_min, input_max) = self.input_iter.size_hint();
+        let min_size = self.buffer.len().saturating_add(input_min);
+        let max_size = match (input_max, self.original_max) {
+            (Some(input_max), Some(original_max)) => {
+                Some(input_max.max(original_max).max(min_size))
+            }
+            _ => None,
+        };
+        (min_size, max_size)
+    }
+    fn nth(&mut self, mut n: usize) -> Option<B::Item> {
+        if self.back_len != 0 {
+            // only the front end's items can be skipped in bulk, and they
+            // might not be at the front of the queue.
+            for _ in 0..n {
+                self.next()?;
+            }
+            return self.next();
+        }
+        loop {
+            // whatever's buffered comes out before anything else, so it can
+            // be skipped in one go.
+            let buffered = self.buffer.len();
+            if n < buffered {
+                for _ in 0..n {
+                    self.buffer.pop();
+                }
+                self.drained = false;
+                return self.buffer.pop();
+            }
+            n -= buffered;
+            self.buffer.clear();
+            match self.input_iter.next() {
+                Some(output) if self.buffer.is_empty() => {
+                    if n == 0 {
+                        self.drained = true;
+                        return Some(B::Item::from_output(output));
+                    }
+                    n -= 1;
+                }
+                // this goes behind the divergent values pulled to get it.
+                Some(output) => self.buffer.push(B::Item::from_output(output)),
+                None if self.buffer.is_empty() => {
+                    self.drained = true;
+                    return None;
+                }
+                None => {}
+            }
+        }
     }
+    fn last(self) -> Option<B::Item> {
+        if self.back_len != 0 {
+            // the back end's items aren't in order in the queue, so leave
+            // putting them in place to `next`.
+            let mut last = None;
+            for next in self {
+                last = Some(next);
+            }
+            return last;
+        }
+        // anything buffered before an output comes out ahead of it, so only
+        // what's still buffered once the chain runs out can follow the last
+        // output.
+        let buffer = self.buffer;
+        let output = self.input_iter.fold(None, |_, output| {
+            buffer.clear();
+            Some(output)
+        });
+        buffer
+            .drain()
+            .last()
+            .or_else(|| output.map(B::Item::from_output))
+    }
+}
+
+impl<I, B> FusedIterator for DefilteredIter<I, B>
+where
+    I: FusedIterator<Item = <B::Item as Try>::Output>,
+    B: ControlFlowBuffer,
+    B::Item: Try,
+{
+}
+
+impl<I, B> DoubleEndedIterator for DefilteredIter<I, B>
+where
+    I: DoubleEndedIterator<Item = <B::Item as Try>::Output>,
+    B: ControlFlowBuffer,
+    B::Item: Try,
+{
+    fn next_back(&mut self) -> Option<B::Item> {
+        // the fast path on the front end only holds while the queue is
+        // empty, which this can't keep track of.
+        self.drained = false;
+        if self.back_len == 0 {
+            // everything buffered is the front end's, and anything the pull
+            // buffers goes in behind it.
+            let front_len = self.buffer.len();
+            match self.input_iter.next_back() {
+                Some(next) if self.buffer.len() == front_len => {
+                    return Some(B::Item::from_output(next));
+                }
+                Some(next) => self.buffer.push(B::Item::from_output(next)),
+                None if self.buffer.len() != front_len => {}
+                None if front_len == 0 => return None,
+                // the source has run out, so the last of the front end's
+                // items is the next one from the back.
+                None => {
+                    rotate(&self.buffer, front_len - 1);
+                    return self.buffer.pop();
+                }
+            }
+            self.back_len = self.buffer.len() - front_len;
+            self.back_first = false;
+        }
+        if !self.back_first {
+            rotate(&self.buffer, self.buffer.len() - self.back_len);
+            self.back_first = true;
+        }
+        self.back_len -= 1;
+        self.buffer.pop()
+    }
+}
+
+/// Moves `n` items from the front of the buffer's queue to the back.
+fn rotate<B: ControlFlowBuffer>(buffer: &B, n: usize) {
+    for _ in 0..n {
+        if let Some(item) = buffer.pop() {
+            buffer.push(item);
+        }
+    }
+}
+
+/// Iterator over the divergent values left in a [`DefilteredIter`]'s buffer.
+/// See [`DefilteredIter::replay_buffer`].
+pub struct ReplayBuffer<Buffer>(Buffer);
+
+impl<B> Iterator for ReplayBuffer<B>
+where
+    B: ControlFlowBuffer,
+    B::Item: Try,
+{
+    type Item = B::Item;
+    fn next(&mut self) -> Option<B::Item> {
+        loop {
+            // normal values queued behind divergent ones are skipped over.
+            if let ControlFlow::Break(residual) = self.0.pop()?.branch() {
+                return Some(B::Item::from_residual(residual));
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.0.len()))
+    }
+}
+
+/// Iterator that knows exactly how many items a recombined iterator has
+/// left, given that it started with one per source item. See
+/// [`crate::IterFiltered::with_filtered_exact`], which is the only way to
+/// make one, since that only holds when the chain gives exactly one output
+/// per normal value.
+#[derive(Clone, Debug)]
+pub struct ExactLen<Input> {
+    inner: Input,
+    remaining: usize,
+}
+
+impl<I: Iterator> ExactLen<I> {
+    #[cfg(feature = "alloc")]
+    pub(crate) fn new(inner: I, len: usize) -> Self {
+        Self {
+            inner,
+            remaining: len,
+        }
+    }
+
+    fn counted(&mut self, next: Option<I::Item>) -> Option<I::Item> {
+        if next.is_some() {
+            self.remaining = self.remaining.saturating_sub(1);
+        }
+        next
+    }
+}
+
+impl<I: Iterator> Iterator for ExactLen<I> {
+    type Item = I::Item;
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        let next = self.inner.next();
+        self.counted(next)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<I: DoubleEndedIterator> DoubleEndedIterator for ExactLen<I> {
+    #[inline]
+    fn next_back(&mut self) -> Option<I::Item> {
+        let next = self.inner.next_back();
+        self.counted(next)
+    }
+}
+
+impl<I: Iterator> ExactSizeIterator for ExactLen<I> {}
+
+impl<I: FusedIterator> FusedIterator for ExactLen<I> {}
+
+/// Alternates between the items of two iterators, one for one, starting
+/// with `a`. These are usually two recombined iterators, in which case each
+/// keeps its own buffer, so a divergent value counts as an item like any
+/// other and comes out on its own side's turn.
+///
+/// Once either side returns `None` it's finished with, and the rest comes
+/// from the other side alone.
+pub fn interleave_filtered<A, B>(a: A, b: B) -> Interleave<A, B>
+where
+    A: Iterator,
+    B: Iterator<Item = A::Item>,
+{
+    Interleave {
+        a: a.fuse(),
+        b: b.fuse(),
+        b_next: false,
+    }
+}
+
+/// Iterator that alternates between two others. See
+/// [`interleave_filtered`].
+#[derive(Clone, Debug)]
+pub struct Interleave<A, B> {
+    a: core::iter::Fuse<A>,
+    b: core::iter::Fuse<B>,
+    b_next: bool,
+}
+
+impl<A, B> Iterator for Interleave<A, B>
+where
+    A: Iterator,
+    B: Iterator<Item = A::Item>,
+{
+    type Item = A::Item;
+    fn next(&mut self) -> Option<A::Item> {
+        self.b_next = !self.b_next;
+        if self.b_next {
+            self.a.next().or_else(|| self.b.next())
+        } else {
+            self.b.next().or_else(|| self.a.next())
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (a_min, a_max) = self.a.size_hint();
+        let (b_min, b_max) = self.b.size_hint();
+        let max = match (a_max, b_max) {
+            (Some(a_max), Some(b_max)) => a_max.checked_add(b_max),
+            _ => None,
+        };
+        (a_min.saturating_add(b_min), max)
+    }
+}
+
+impl<A, B> FusedIterator for Interleave<A, B>
+where
+    A: Iterator,
+    B: Iterator<Item = A::Item>,
+{
+}
+
+/// Iterator that gives out all of a chain's output over a [`FilteredIter`]
+/// first, and only then the divergent values it buffered, in the order they
+/// were found. See [`crate::IterFiltered::with_filtered_flush_at_end`].
+///
+/// Unlike [`DefilteredIter`], this holds on to every divergent value in
+/// the source until the chain runs out, so the buffer should be one that
+/// can grow that far.
+pub struct FlushedIter<Input, Buffer> {
+    input_iter: Input,
+    buffer: Buffer,
+}
+
+impl<I, B> FlushedIter<I, B>
+where
+    I: Iterator<Item = <B::Item as Try>::Output>,
+    B: ControlFlowBuffer,
+    B::Item: Try,
+{
+    pub fn new(input_iter: I, buffer: B) -> Self {
+        Self { input_iter, buffer }
+    }
+}
+
+impl<I, B> Iterator for FlushedIter<I, B>
+where
+    I: Iterator<Item = <B::Item as Try>::Output>,
+    B: ControlFlowBuffer,
+    B::Item: Try,
+{
+    type Item = B::Item;
+    #[inline]
+    fn next(&mut self) -> Option<B::Item> {
+        match self.input_iter.next() {
+            Some(next) => Some(B::Item::from_output(next)),
+            None => self.buffer.pop(),
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // more divergent values can turn up for as long as the chain has
+        // anything left, so there's only an upper bound once it's done.
+        let (input_min, input_max) = self.input_iter.size_hint();
+        let buffered = self.buffer.len();
+        let max = match input_max {
+            Some(0) => Some(buffered),
+            _ => None,
+        };
+        (input_min.saturating_add(buffered), max)
+    }
+}
+
+impl<I, B> FusedIterator for FlushedIter<I, B>
+where
+    I: FusedIterator<Item = <B::Item as Try>::Output>,
+    B: ControlFlowBuffer,
+    B::Item: Try,
+{
 }
 
 #[cfg(test)]
 pub mod tests {
-    pub fn result_samples() -> impl Iterator<Item = Result<u32, &'static str>> {
+    pub fn result_samples() -> core::array::IntoIter<Result<u32, &'static str>, 9> {
         [
             Ok(1),
             Ok(2),
@@ -110,6 +1197,21 @@ pub mod tests {
         .into_iter()
     }
 
+    pub fn option_samples() -> core::array::IntoIter<Option<u32>, 9> {
+        [
+            Some(1),
+            Some(2),
+            Some(1),
+            None,
+            None,
+            Some(3),
+            Some(1),
+            None,
+            Some(5),
+        ]
+        .into_iter()
+    }
+
     #[cfg(feature = "std")]
     mod is_std {
         use super::{super::*, *};
@@ -147,5 +1249,587 @@ pub mod tests {
             assert_eq!(buf.next_wrapped(&mut filtered), Some(Ok(5)));
             assert_eq!(buf.next_wrapped(&mut filtered), None);
         }
+
+        fn assert_fused<I: FusedIterator>(iter: I) -> I {
+            iter
+        }
+
+        #[test]
+        fn filtered_stays_exhausted() {
+            let mut filtered = assert_fused(FilteredIter::new(result_samples(), Buffer::default()));
+
+            assert_eq!(filtered.by_ref().count(), 6);
+            assert_eq!(filtered.next(), None);
+            assert_eq!(filtered.next(), None);
+        }
+
+        #[test]
+        fn filtered_clone_is_independent() {
+            let buf = Buffer::default();
+            let mut filtered = FilteredIter::new(result_samples(), buf.clone());
+            assert_eq!(filtered.next(), Some(1));
+            assert_eq!(filtered.next(), Some(2));
+            assert_eq!(filtered.next(), Some(1));
+            assert_eq!(filtered.next(), Some(3));
+            assert_eq!(buf.len(), 2);
+
+            let cloned = filtered.clone();
+            assert_eq!(filtered.collect::<Vec<_>>(), [1, 5]);
+            assert_eq!(buf.len(), 3);
+
+            // the clone started with its own copy of the two buffered items,
+            // and adds "zoop" only to that copy.
+            let cloned_buf = cloned.buffer.clone();
+            assert_eq!(cloned.collect::<Vec<_>>(), [1, 5]);
+            assert_eq!(buf.len(), 3);
+            assert_eq!(
+                cloned_buf.drain().collect::<Vec<_>>(),
+                [Err("boom"), Err("hi"), Err("zoop")]
+            );
+        }
+
+        #[test]
+        fn filtered_map_residual_changes_buffered_values() {
+            let buf = Buffer::default();
+            let filtered = FilteredIter::new(result_samples(), buf.clone())
+                .map_residual(|err| err.map_err(|s| format!("mapped {}", s)));
+            let output: Vec<_> =
+                DefilteredIter::new(filtered.filter(|n| *n > 2), buf, None).collect();
+
+            assert_eq!(
+                output,
+                [
+                    Err("mapped boom".to_string()),
+                    Err("mapped hi".to_string()),
+                    Ok(3),
+                    Err("mapped zoop".to_string()),
+                    Ok(5),
+                ]
+            );
+        }
+
+        /// A [`Try`] type whose residual keeps only the error code, so that
+        /// rebuilding it from that loses the message it came with.
+        #[derive(Debug, PartialEq)]
+        enum Reported {
+            Fine(u32),
+            Failed(u32, &'static str),
+        }
+
+        struct ErrorCode(u32);
+
+        #[cfg(feature = "nightly")]
+        impl core::ops::Residual<u32> for ErrorCode {
+            type TryType = Reported;
+        }
+
+        impl Try for Reported {
+            type Output = u32;
+            type Residual = ErrorCode;
+
+            fn from_output(output: u32) -> Self {
+                Reported::Fine(output)
+            }
+            fn branch(self) -> ControlFlow<ErrorCode, u32> {
+                match self {
+                    Reported::Fine(n) => ControlFlow::Continue(n),
+                    Reported::Failed(code, _) => ControlFlow::Break(ErrorCode(code)),
+                }
+            }
+        }
+
+        impl FromResidual<ErrorCode> for Reported {
+            fn from_residual(ErrorCode(code): ErrorCode) -> Self {
+                Reported::Failed(code, "rebuilt")
+            }
+        }
+
+        impl BranchIntact for Reported {
+            fn branch_intact(self) -> ControlFlow<Self, u32> {
+                match self {
+                    Reported::Fine(n) => ControlFlow::Continue(n),
+                    failed => ControlFlow::Break(failed),
+                }
+            }
+        }
+
+        fn reported_samples() -> impl Iterator<Item = Reported> {
+            [
+                Reported::Fine(1),
+                Reported::Failed(404, "not found"),
+                Reported::Fine(2),
+            ]
+            .into_iter()
+        }
+
+        #[test]
+        fn filtered_rebuilds_from_residual() {
+            let buf = Buffer::default();
+            let filtered = FilteredIter::new(reported_samples(), buf.clone());
+            let output: Vec<_> = DefilteredIter::new(filtered, buf, None).collect();
+
+            assert_eq!(
+                output,
+                [
+                    Reported::Fine(1),
+                    Reported::Failed(404, "rebuilt"),
+                    Reported::Fine(2),
+                ]
+            );
+        }
+
+        #[test]
+        fn filtered_buffer_intact_keeps_divergents_whole() {
+            let buf = Buffer::default();
+            let filtered = FilteredIter::new(reported_samples(), buf.clone()).buffer_intact();
+            let output: Vec<_> = DefilteredIter::new(filtered, buf, None).collect();
+
+            assert_eq!(
+                output,
+                [
+                    Reported::Fine(1),
+                    Reported::Failed(404, "not found"),
+                    Reported::Fine(2),
+                ]
+            );
+        }
+
+        #[test]
+        fn filtered_from_fn() {
+            let buf = Buffer::default();
+            let mut count = 0;
+            let mut filtered = FilteredIter::from_fn(
+                || {
+                    count += 1;
+                    match count {
+                        1 | 4 => Some(Ok(count)),
+                        2 | 3 => Some(Err(count)),
+                        _ => None,
+                    }
+                },
+                buf.clone(),
+            );
+
+            assert_eq!(filtered.next(), Some(1));
+            assert!(buf.is_empty());
+            assert_eq!(filtered.next(), Some(4));
+            assert_eq!(filtered.next(), None);
+            assert_eq!(buf.drain().collect::<Vec<_>>(), [Err(2), Err(3)]);
+        }
+
+        #[test]
+        fn filtered_windows() {
+            use crate::tagged::Item::{Divergent, Value};
+
+            let output: Vec<_> = FilteredIter::new(result_samples(), Buffer::default())
+                .windows(2)
+                .collect();
+            // the windows start over after each run of divergent values, and
+            // the lone 5 at the end never fills one.
+            assert_eq!(
+                output,
+                [
+                    Value(vec![1, 2]),
+                    Value(vec![2, 1]),
+                    Divergent(Err("boom")),
+                    Divergent(Err("hi")),
+                    Value(vec![3, 1]),
+                    Divergent(Err("zoop")),
+                ]
+            );
+
+            // the divergent values come out ahead of the value pulled past them.
+            let source = [Ok(1), Err("boom"), Ok(2), Err("zoop")].into_iter();
+            let output: Vec<_> = FilteredIter::new(source, Buffer::default())
+                .windows(1)
+                .collect();
+            assert_eq!(
+                output,
+                [
+                    Value(vec![1]),
+                    Divergent(Err("boom")),
+                    Value(vec![2]),
+                    Divergent(Err("zoop")),
+                ]
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "window size must be non-zero")]
+        fn filtered_windows_of_nothing() {
+            let buf: Buffer<Result<u32, &str>> = Buffer::default();
+            FilteredIter::new(result_samples(), buf).windows(0);
+        }
+
+        #[test]
+        fn filtered_into_inner_returns_remaining_source() {
+            let mut filtered = FilteredIter::new(result_samples(), Buffer::default());
+            assert_eq!(filtered.next(), Some(1));
+            assert_eq!(filtered.next(), Some(2));
+
+            let (source, buf) = filtered.into_inner();
+            assert!(buf.is_empty());
+            assert_eq!(
+                source.collect::<Vec<_>>(),
+                [
+                    Ok(1),
+                    Err("boom"),
+                    Err("hi"),
+                    Ok(3),
+                    Ok(1),
+                    Err("zoop"),
+                    Ok(5)
+                ]
+            );
+        }
+
+        #[test]
+        fn filtered_reversed_gives_normal_items_backwards() {
+            let buf = Buffer::default();
+            let forward: Vec<_> = FilteredIter::new(result_samples(), buf.clone()).collect();
+            let mut backward: Vec<_> = FilteredIter::new(result_samples(), buf.clone())
+                .rev()
+                .collect();
+            backward.reverse();
+
+            assert_eq!(forward, backward);
+            assert_eq!(buf.len(), 6);
+        }
+
+        #[test]
+        fn filtered_reversed_recombines_in_reverse() {
+            use crate::IterFiltered;
+
+            let mut expected: Vec<_> = result_samples().collect();
+            expected.reverse();
+            let reversed: Vec<_> = result_samples().with_filtered(|i| i.rev()).collect();
+
+            assert_eq!(reversed, expected);
+        }
+
+        #[test]
+        fn defiltered_counts_divergents_seen() {
+            use crate::IterFiltered;
+
+            let mut output = result_samples().with_filtered(|i| i.filter(|n| *n > 2));
+            assert_eq!(output.divergents_seen(), Some(0));
+            output.by_ref().for_each(drop);
+            assert_eq!(output.divergents_seen(), Some(3));
+
+            let buf = crate::ArrayBuffer::<_, 4>::new();
+            let output = result_samples().with_filtered_buf(&buf, |i| i);
+            assert_eq!(output.divergents_seen(), None);
+        }
+
+        #[test]
+        fn defiltered_stays_exhausted() {
+            use crate::IterFiltered;
+
+            let mut output = assert_fused(result_samples().with_filtered(|i| i.filter(|n| *n > 2)));
+            assert_eq!(output.by_ref().count(), 5);
+            assert_eq!(output.next(), None);
+            assert_eq!(output.next(), None);
+        }
+
+        #[test]
+        fn defiltered_debug() {
+            use crate::IterFiltered;
+
+            let mut output = result_samples().with_filtered(|i| i.filter(|n| *n > 2));
+            output.next();
+            assert_eq!(
+                format!("{:?}", output),
+                "DefilteredIter { input_iter: .., buffered: 2, original_max: Some(9) }"
+            );
+        }
+
+        #[test]
+        fn defiltered_reversed() {
+            use crate::IterFiltered;
+
+            let forward: Vec<_> = result_samples()
+                .with_filtered(|i| i.map(|n| n * 10))
+                .collect();
+            let backward: Vec<_> = result_samples()
+                .with_filtered(|i| i.map(|n| n * 10))
+                .rev()
+                .collect();
+
+            assert_eq!(
+                forward,
+                [
+                    Ok(10),
+                    Ok(20),
+                    Ok(10),
+                    Err("boom"),
+                    Err("hi"),
+                    Ok(30),
+                    Ok(10),
+                    Err("zoop"),
+                    Ok(50)
+                ]
+            );
+            assert_eq!(
+                backward,
+                [
+                    Ok(50),
+                    Err("zoop"),
+                    Ok(10),
+                    Ok(30),
+                    Err("hi"),
+                    Err("boom"),
+                    Ok(10),
+                    Ok(20),
+                    Ok(10)
+                ]
+            );
+        }
+
+        #[test]
+        fn defiltered_from_both_ends() {
+            use crate::IterFiltered;
+
+            let mut output = result_samples().with_filtered(|i| i.filter(|n| *n != 1));
+            assert_eq!(output.next_back(), Some(Ok(5)));
+            assert_eq!(output.next_back(), Some(Err("zoop")));
+            assert_eq!(output.next_back(), Some(Ok(3)));
+            assert_eq!(output.next(), Some(Ok(2)));
+            assert_eq!(output.next(), Some(Err("boom")));
+            assert_eq!(output.next_back(), Some(Err("hi")));
+            assert_eq!(output.next(), None);
+            assert_eq!(output.next_back(), None);
+        }
+
+        #[test]
+        fn defiltered_mixing_ends() {
+            use crate::IterFiltered;
+
+            // every way of pulling from the two ends should give the forward
+            // order back, once what came off the back is reversed.
+            fn check<O, F>(f: F)
+            where
+                F: FnMut(
+                        FilteredIter<
+                            core::array::IntoIter<Result<u32, &'static str>, 9>,
+                            Buffer<Result<u32, &'static str>>,
+                        >,
+                    ) -> O
+                    + Clone,
+                O: DoubleEndedIterator<Item = u32>,
+            {
+                let forward: Vec<_> = result_samples().with_filtered(f.clone()).collect();
+                for pattern in 0..1u32 << forward.len() {
+                    let mut output = result_samples().with_filtered(f.clone());
+                    let (mut front, mut back) = (vec![], vec![]);
+                    for step in 0..forward.len() {
+                        if pattern & 1 << step == 0 {
+                            front.extend(output.next());
+                        } else {
+                            back.extend(output.next_back());
+                        }
+                    }
+                    assert_eq!(output.next(), None, "pattern {:b}", pattern);
+                    assert_eq!(output.next_back(), None, "pattern {:b}", pattern);
+                    front.extend(back.into_iter().rev());
+                    assert_eq!(front, forward, "pattern {:b}", pattern);
+                }
+            }
+            check(|i| i);
+            check(|i| i.filter(|n| *n != 1));
+            check(|i| i.map(|n| n * 10));
+
+            // walking through a run the back end buffered from the front.
+            let mut output = [Ok(1), Err("a"), Err("b"), Ok(2), Err("c")]
+                .into_iter()
+                .with_filtered(|i| i);
+            assert_eq!(output.next_back(), Some(Err("c")));
+            assert_eq!(output.next_back(), Some(Ok(2)));
+            assert_eq!(output.next_back(), Some(Err("b")));
+            assert_eq!(output.next(), Some(Ok(1)));
+            assert_eq!(output.next(), Some(Err("a")));
+            assert_eq!(output.next(), None);
+        }
+
+        #[test]
+        fn defiltered_consumers_after_next_back() {
+            use crate::IterFiltered;
+
+            let source = [Ok(1), Err("a"), Err("b"), Ok(2), Err("c")];
+            let started = || {
+                let mut output = source.into_iter().with_filtered(|i| i);
+                assert_eq!(output.next_back(), Some(Err("c")));
+                assert_eq!(output.next_back(), Some(Ok(2)));
+                assert_eq!(output.next_back(), Some(Err("b")));
+                output
+            };
+            assert_eq!(started().collect::<Vec<_>>(), [Ok(1), Err("a")]);
+            assert_eq!(started().last(), Some(Err("a")));
+            assert_eq!(started().nth(1), Some(Err("a")));
+            let output: Result<Vec<u32>, &str> = started().into_result_vec();
+            assert_eq!(output, Err("a"));
+        }
+
+        #[test]
+        fn defiltered_lower_bound_counts_buffered() {
+            use crate::IterFiltered;
+
+            let mut output = result_samples().with_filtered(|i| i.filter(|n| *n > 2));
+            assert_eq!(output.size_hint().0, 0);
+
+            assert_eq!(output.next(), Some(Err("boom")));
+            // "hi" and Ok(3) are now waiting in the buffer.
+            assert_eq!(output.size_hint().0, 2);
+        }
+
+        #[test]
+        fn defiltered_upper_bound_follows_unbounded_sides() {
+            use crate::IterFiltered;
+
+            // the chain can give out more than the source had.
+            let source = [Ok(1), Ok(2), Err("a"), Ok(3), Ok(4), Ok(5), Ok(6)];
+            let output = source
+                .into_iter()
+                .with_filtered(|i| i.flat_map(|n| vec![n, n + 100]));
+            assert_eq!(output.size_hint(), (0, None));
+            assert_eq!(output.count(), 13);
+
+            // and an unbounded source any number of divergent values, even
+            // when the chain stops early.
+            let output = (0..)
+                .map(|n| if n % 2 == 0 { Err(n) } else { Ok(n) })
+                .with_filtered(|i| i.take(2));
+            assert_eq!(output.size_hint(), (0, None));
+            assert_eq!(output.count(), 4);
+        }
+
+        #[test]
+        fn defiltered_keeps_external_pushes_in_order() {
+            use crate::IterFiltered;
+            use core::cell::Cell;
+
+            let buf = Buffer::default();
+            let pulled = Cell::new(0);
+            let mut output = [Ok(1), Ok(2), Err("boom"), Ok(3)]
+                .into_iter()
+                .inspect(|_| pulled.set(pulled.get() + 1))
+                .with_filtered_buf(buf.clone(), |i| i);
+            assert_eq!(output.next(), Some(Ok(1)));
+
+            // pushed while the last call left the buffer empty.
+            buf.extend_back([Err("x"), Err("y")]);
+            assert_eq!(output.next(), Some(Err("x")));
+            // the source was advanced to get past the fast path.
+            assert_eq!(pulled.get(), 2);
+            assert_eq!(output.next(), Some(Err("y")));
+            assert_eq!(output.next(), Some(Ok(2)));
+
+            // and pushed while it wasn't empty.
+            assert_eq!(output.next(), Some(Err("boom")));
+            buf.push(Err("z"));
+            assert_eq!(output.next(), Some(Ok(3)));
+            assert_eq!(output.next(), Some(Err("z")));
+            buf.push(Err("last"));
+            assert_eq!(output.next(), Some(Err("last")));
+            assert_eq!(output.next(), None);
+        }
+
+        #[test]
+        fn defiltered_skips_pops_on_normal_runs() {
+            use crate::IterFiltered;
+            use core::cell::Cell;
+            use std::rc::Rc;
+
+            #[derive(Clone)]
+            struct CountingBuffer(Buffer<Result<u32, &'static str>>, Rc<Cell<usize>>);
+            impl ControlFlowBuffer for CountingBuffer {
+                type Item = Result<u32, &'static str>;
+
+                fn push(&self, input: Self::Item) {
+                    self.0.push(input)
+                }
+                fn pop(&self) -> Option<Self::Item> {
+                    self.1.set(self.1.get() + 1);
+                    self.0.pop()
+                }
+                fn peek_front(&self) -> Option<Self::Item> {
+                    self.0.peek_front()
+                }
+                fn len(&self) -> usize {
+                    self.0.len()
+                }
+            }
+
+            let pops = Rc::new(Cell::new(0));
+            let buf = CountingBuffer(Buffer::default(), pops.clone());
+            let output: Vec<_> = (0..100)
+                .map(Ok)
+                .with_filtered_buf(buf.clone(), |i| i.map(|n| n + 1))
+                .collect();
+            assert_eq!(output, (1..101).map(Ok).collect::<Vec<_>>());
+            // one pop per item, plus the first and last calls.
+            assert_eq!(pops.get(), 102);
+
+            // still correct when divergent values do turn up.
+            let output: Vec<_> = (0..100)
+                .map(|n| if n % 40 == 39 { Err("boom") } else { Ok(n) })
+                .with_filtered_buf(buf, |i| i.filter(|n| n % 10 == 0))
+                .collect();
+            assert_eq!(
+                output,
+                [
+                    Ok(0),
+                    Ok(10),
+                    Ok(20),
+                    Ok(30),
+                    Err("boom"),
+                    Ok(40),
+                    Ok(50),
+                    Ok(60),
+                    Ok(70),
+                    Err("boom"),
+                    Ok(80),
+                    Ok(90)
+                ]
+            );
+        }
+
+        #[test]
+        fn defiltered_refilter() {
+            use crate::IterFiltered;
+
+            let output: Vec<_> = result_samples()
+                .with_filtered(|i| i.filter(|n| *n > 1))
+                .refilter(|i| i.map(|n| n * 2))
+                .refilter(|i| i.filter(|n| *n != 6))
+                .collect();
+
+            assert_eq!(output, [Ok(4), Err("boom"), Err("hi"), Err("zoop"), Ok(10)]);
+        }
+
+        #[test]
+        fn defiltered_clone_is_independent() {
+            use crate::IterFiltered;
+
+            let mut output = result_samples().with_filtered(|i| i.filter(|n| *n > 2));
+            assert_eq!(output.next(), Some(Err("boom")));
+
+            let copy = output.clone();
+            let rest = [Err("hi"), Ok(3), Err("zoop"), Ok(5)];
+            assert_eq!(copy.collect::<Vec<_>>(), rest);
+            assert_eq!(output.collect::<Vec<_>>(), rest);
+        }
+
+        #[test]
+        fn defiltered_lower_bound_adds_input_lower_bound() {
+            use crate::IterFiltered;
+
+            let mut output = result_samples().with_filtered(|i| i.chain([7, 8]));
+            assert_eq!(output.size_hint().0, 2);
+
+            assert_eq!(output.nth(3), Some(Err("boom")));
+            // "hi" and Ok(3) are buffered, and the chain still has 7 and 8.
+            assert_eq!(output.size_hint().0, 4);
+            assert_eq!(output.count(), 7);
+        }
     }
 }
@@ -0,0 +1,216 @@
+/*
+ Copyright 2022 The Iteritor Authors
+
+ Licensed under the Apache License, Version 2.0 (the "License");
+ you may not use this file except in compliance with the License.
+ You may obtain a copy of the License at
+
+      https://www.apache.org/licenses/LICENSE-2.0
+
+ Unless required by applicable law or agreed to in writing, software
+ distributed under the License is distributed on an "AS IS" BASIS,
+ WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ See the License for the specific language governing permissions and
+ limitations under the License.
+ */
+
+//! Grouping runs of normal values inside a [`FilteredIter`].
+//!
+//! Itertools' [`Itertools::chunk_by`] can't be used directly inside the
+//! closure given to [`crate::IterFiltered::with_filtered`], since the groups
+//! it hands out borrow the `ChunkBy` itself, which would have to outlive the
+//! closure. [`FilteredIter::chunks_by`] gives out owned groups instead, and
+//! doesn't need itertools at all.
+//!
+//! The groups aren't the same type as the source's normal values, so this
+//! goes with [`crate::IterFiltered::with_filtered_into`], recombining into
+//! a `Result<(K, Vec<U>), E>` (or similar) rather than the source's type.
+//!
+//! A group only ends once the first value after it has been seen, so the
+//! divergent values between one group and the next are pulled while
+//! finishing the first. They're held back until that group has been given
+//! out, so they come out between the two. Divergent values never split a
+//! group: normal values either side of one with the same key end up in the
+//! same group, and the divergent value comes out after it.
+//!
+//! [`Itertools::chunk_by`]: https://docs.rs/itertools/latest/itertools/trait.Itertools.html#method.chunk_by
+
+use alloc::{vec, vec::Vec};
+
+use crate::filtered::FilteredIter;
+use crate::residual::ResidualMap;
+use crate::try_polyfill::Try;
+use crate::ControlFlowBuffer;
+
+/// An iterator of `(key, group)` pairs, one for each run of consecutive
+/// normal values for which `key` gives the same value. See
+/// [`FilteredIter::chunks_by`].
+pub struct ChunksBy<I, B, M, F>
+where
+    I: Iterator,
+    I::Item: Try,
+    B: ControlFlowBuffer,
+{
+    inner: FilteredIter<I, B, M>,
+    key: F,
+    // the value that ended the last group, which starts the next one.
+    next_first: Option<<I::Item as Try>::Output>,
+    // divergent values pulled while finishing the last group, held back so
+    // they come out after it.
+    held: Vec<B::Item>,
+}
+
+impl<I, B, M, F> ChunksBy<I, B, M, F>
+where
+    I: Iterator,
+    I::Item: Try,
+    B: ControlFlowBuffer,
+{
+    pub fn new(inner: FilteredIter<I, B, M>, key: F) -> Self {
+        Self {
+            inner,
+            key,
+            next_first: None,
+            held: Vec::new(),
+        }
+    }
+}
+
+impl<I, B, M, F, T, K> Iterator for ChunksBy<I, B, M, F>
+where
+    I: Iterator<Item = T>,
+    T: Try,
+    B: ControlFlowBuffer,
+    M: ResidualMap<T, Output = B::Item>,
+    F: FnMut(&T::Output) -> K,
+    K: PartialEq,
+{
+    type Item = (K, Vec<T::Output>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // what the last group held back goes ahead of anything pulled now.
+        self.inner.buffer().extend_back(self.held.drain(..));
+        let first = match self.next_first.take() {
+            Some(first) => first,
+            None => self.inner.next()?,
+        };
+        let before = self.inner.buffer().len();
+
+        let key = (self.key)(&first);
+        let mut group = vec![first];
+        for item in self.inner.by_ref() {
+            if (self.key)(&item) == key {
+                group.push(item);
+            } else {
+                self.next_first = Some(item);
+                break;
+            }
+        }
+
+        // anything buffered while finishing the group belongs after it, so
+        // take it back off the end of the queue until the group's out.
+        let buffer = self.inner.buffer();
+        let after = buffer.len() - before;
+        for _ in 0..before {
+            if let Some(item) = buffer.pop() {
+                buffer.push(item);
+            }
+        }
+        self.held.extend((0..after).filter_map(|_| buffer.pop()));
+        Some((key, group))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (min, max) = self.inner.size_hint();
+        match self.next_first {
+            Some(_) => (1, max.and_then(|max| max.checked_add(1))),
+            None => (min.min(1), max),
+        }
+    }
+}
+
+impl<I, B, M> FilteredIter<I, B, M>
+where
+    I: Iterator,
+    I::Item: Try,
+    B: ControlFlowBuffer,
+{
+    /// Groups consecutive normal values by `key`, like
+    /// [`Itertools::chunk_by`], but with each group collected into a `Vec`
+    /// so it can be returned from the filtering closure. See the
+    /// [module docs](self) for how groups line up with the divergent values
+    /// around them.
+    ///
+    /// [`Itertools::chunk_by`]: https://docs.rs/itertools/latest/itertools/trait.Itertools.html#method.chunk_by
+    pub fn chunks_by<K, F>(self, key: F) -> ChunksBy<I, B, M, F>
+    where
+        F: FnMut(&<I::Item as Try>::Output) -> K,
+        K: PartialEq,
+    {
+        ChunksBy::new(self, key)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{filtered::tests::result_samples, IterFiltered};
+
+    #[test]
+    fn chunks_by_keeps_divergents_between_groups() {
+        let output: Vec<Result<(bool, Vec<u32>), &str>> = result_samples()
+            .with_filtered_into(|i| i.chunks_by(|n| *n < 3))
+            .collect();
+
+        assert_eq!(
+            output,
+            [
+                Ok((true, vec![1, 2, 1])),
+                Err("boom"),
+                Err("hi"),
+                Ok((false, vec![3])),
+                Ok((true, vec![1])),
+                Err("zoop"),
+                Ok((false, vec![5])),
+            ]
+        );
+    }
+
+    #[test]
+    fn chunks_by_joins_across_divergents() {
+        let output: Vec<Result<(u32, Vec<u32>), &str>> = [Ok(1), Err("boom"), Ok(1), Ok(2)]
+            .into_iter()
+            .with_filtered_into(|i| i.chunks_by(|n| *n))
+            .collect();
+
+        assert_eq!(output, [Ok((1, vec![1, 1])), Err("boom"), Ok((2, vec![2]))]);
+    }
+
+    #[test]
+    fn chunks_by_orders_divergents_at_the_ends() {
+        let output: Vec<Result<(u32, Vec<u32>), &str>> = [
+            Err("first"),
+            Ok(1),
+            Ok(2),
+            Err("a"),
+            Err("b"),
+            Ok(3),
+            Err("last"),
+        ]
+        .into_iter()
+        .with_filtered_into(|i| i.chunks_by(|n| *n))
+        .collect();
+
+        assert_eq!(
+            output,
+            [
+                Err("first"),
+                Ok((1, vec![1])),
+                Ok((2, vec![2])),
+                Err("a"),
+                Err("b"),
+                Ok((3, vec![3])),
+                Err("last"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,153 @@
+/*
+ Copyright 2022 The Iteritor Authors
+
+ Licensed under the Apache License, Version 2.0 (the "License");
+ you may not use this file except in compliance with the License.
+ You may obtain a copy of the License at
+
+      https://www.apache.org/licenses/LICENSE-2.0
+
+ Unless required by applicable law or agreed to in writing, software
+ distributed under the License is distributed on an "AS IS" BASIS,
+ WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ See the License for the specific language governing permissions and
+ limitations under the License.
+ */
+
+//! Support for carrying each item's position in the source alongside it,
+//! used by [`crate::IterFiltered::with_filtered_indexed`] and
+//! [`crate::IterFiltered::with_filtered_ordered`].
+
+use core::{iter::FusedIterator, ops::ControlFlow};
+
+use crate::residual::BranchIntact;
+use crate::try_polyfill::{FromResidual, Try};
+
+/// A source item paired with its position in the source. This is itself a
+/// [`Try`] type, with both its output and residual ([`IndexedResidual`])
+/// carrying the index, so an indexed divergent value can be buffered and
+/// rebuilt like any other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Indexed<T>(pub usize, pub T);
+
+impl<T> Indexed<T> {
+    pub fn into_inner(self) -> (usize, T) {
+        (self.0, self.1)
+    }
+}
+
+/// The residual of an [`Indexed`] value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexedResidual<R>(pub usize, pub R);
+
+#[cfg(feature = "nightly")]
+impl<R, O> core::ops::Residual<(usize, O)> for IndexedResidual<R>
+where
+    R: core::ops::Residual<O>,
+{
+    type TryType = Indexed<R::TryType>;
+}
+
+impl<T: Try> Try for Indexed<T> {
+    type Output = (usize, T::Output);
+    type Residual = IndexedResidual<T::Residual>;
+
+    fn from_output((index, output): Self::Output) -> Self {
+        Self(index, T::from_output(output))
+    }
+    fn branch(self) -> ControlFlow<Self::Residual, Self::Output> {
+        match self.1.branch() {
+            ControlFlow::Continue(output) => ControlFlow::Continue((self.0, output)),
+            ControlFlow::Break(residual) => ControlFlow::Break(IndexedResidual(self.0, residual)),
+        }
+    }
+}
+
+impl<T: Try> FromResidual<IndexedResidual<T::Residual>> for Indexed<T> {
+    fn from_residual(IndexedResidual(index, residual): IndexedResidual<T::Residual>) -> Self {
+        Self(index, T::from_residual(residual))
+    }
+}
+
+impl<T: BranchIntact> BranchIntact for Indexed<T> {
+    fn branch_intact(self) -> ControlFlow<Self, Self::Output> {
+        match self.1.branch_intact() {
+            ControlFlow::Continue(output) => ControlFlow::Continue((self.0, output)),
+            ControlFlow::Break(item) => ControlFlow::Break(Self(self.0, item)),
+        }
+    }
+}
+
+/// Wraps each item of an iterator in an [`Indexed`] with its position.
+#[derive(Clone, Debug)]
+pub struct IndexedIter<Input> {
+    input_iter: Input,
+    index: usize,
+}
+
+impl<I: Iterator> IndexedIter<I> {
+    pub fn new(input_iter: I) -> Self {
+        Self {
+            input_iter,
+            index: 0,
+        }
+    }
+}
+
+impl<I: Iterator> Iterator for IndexedIter<I> {
+    type Item = Indexed<I::Item>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.input_iter.next()?;
+        let index = self.index;
+        self.index += 1;
+        Some(Indexed(index, next))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input_iter.size_hint()
+    }
+}
+
+impl<I: FusedIterator> FusedIterator for IndexedIter<I> {}
+
+/// Drops the indices from an iterator of `(index, item)` pairs, checking
+/// with a debug assertion that they never go down. See
+/// [`crate::IterFiltered::with_filtered_ordered`].
+#[derive(Clone, Debug)]
+pub struct OrderChecked<Input> {
+    input_iter: Input,
+    last: Option<usize>,
+}
+
+impl<I> OrderChecked<I> {
+    pub fn new(input_iter: I) -> Self {
+        Self {
+            input_iter,
+            last: None,
+        }
+    }
+}
+
+impl<I, T> Iterator for OrderChecked<I>
+where
+    I: Iterator<Item = (usize, T)>,
+{
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let (index, next) = self.input_iter.next()?;
+        if let Some(last) = self.last {
+            debug_assert!(
+                index >= last,
+                "item from source index {} came out after index {}",
+                index,
+                last
+            );
+        }
+        self.last = Some(index);
+        Some(next)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input_iter.size_hint()
+    }
+}
+
+impl<I, T> FusedIterator for OrderChecked<I> where I: FusedIterator<Item = (usize, T)> {}
@@ -14,18 +14,41 @@
  limitations under the License.
  */
 
-#![cfg_attr(feature = "nightly", feature(try_trait_v2))]
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "nightly", feature(try_trait_v2, try_trait_v2_residual))]
+
+#[cfg(any(feature = "alloc", feature = "rayon"))]
+extern crate alloc;
 
 pub mod breaking;
 pub mod buffer;
+pub mod builder;
 pub mod filtered;
+#[cfg(feature = "alloc")]
+pub mod grouping;
+pub mod indexed;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod partition;
+pub mod residual;
+pub mod tagged;
 pub mod try_polyfill;
 
 use breaking::*;
 use buffer::*;
 use filtered::*;
+#[cfg(feature = "alloc")]
+use indexed::*;
+use partition::*;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
 
-use try_polyfill::Try;
+use try_polyfill::{FromResidual, Try};
+
+#[cfg(feature = "derive")]
+pub use iteritor_derive::Tryable;
 
 /// Including this trait adds the `with_filtered` (and `with_filtered_buf` for
 /// nostd use) helper function to any normal iterator.
@@ -40,6 +63,15 @@ use try_polyfill::Try;
 /// in a deque, avoiding the need to `.collect()` mid-processing to get at 
 /// the values in a complicated chain and still use normal combinators.
 /// 
+/// All of these are lazy: setting up the pipeline only looks at the
+/// source's `size_hint`, and nothing is pulled from it until the returned
+/// iterator is first advanced (unless the chain built in `f` pulls on its
+/// own, say by peeking).
+///
+/// The source is just an iterator, so sources joined with `chain` before
+/// filtering recombine as one: divergent values from either side of the
+/// join come out in order, and the source's size hint is the sum of both.
+///
 #[cfg_attr(feature = "std", doc = r##"
 Example:
 
@@ -94,12 +126,17 @@ pub trait IterFiltered<T>: Iterator {
     /// the skipped divergent values and the results of the iterator chain in
     /// the function on the normal values.
     /// 
+    /// Ordering is 'errors first' with respect to each pull: when the chain
+    /// skips over divergent values on its way to the next normal value, all
+    /// of them are given out before whatever the chain makes of that value.
+    /// See [`filtered::DefilteredIter`] for the details.
+    ///
     /// This variant is mostly for use in no std/no alloc code where you can
     /// supply your own (maybe fixed-size) buffer implementation.
     fn with_filtered_buf<B, O, U, F>(self, buf: B, f: F) -> DefilteredIter<O, B>
     where
         Self: Iterator<Item = T> + Sized,
-        F: Fn(FilteredIter<Self, B>) -> O,
+        F: FnMut(FilteredIter<Self, B>) -> O,
         O: Iterator<Item = U>,
         T: Try<Output = U>,
         B: ControlFlowBuffer<Item = T>;
@@ -110,16 +147,327 @@ pub trait IterFiltered<T>: Iterator {
     /// An iterator will be returned that includes, in order, a combination of
     /// the skipped divergent values and the results of the iterator chain in
     /// the function on the normal values.
-    #[cfg(feature = "std")]
+    #[cfg(feature = "alloc")]
     fn with_filtered<O, U, F>(self, f: F) -> DefilteredIter<O, buffer::Buffer<T>>
     where
         Self: Iterator<Item = T> + Sized,
-        F: Fn(FilteredIter<Self, buffer::Buffer<T>>) -> O,
+        F: FnMut(FilteredIter<Self, buffer::Buffer<T>>) -> O,
         O: Iterator<Item = U>,
         T: Try<Output = U>,
     {
         self.with_filtered_buf(buffer::Buffer::default(), f)
     }
+
+    /// Like [`IterFiltered::with_filtered`], but the recombined items are of
+    /// some other [`Try`] type `R`, and the chain in `f` yields its output.
+    /// Divergent values are converted into `R` with [`FromResidual`] as
+    /// they're found, the same way the `?` operator would, so a source of
+    /// `Result<T, E1>` can come back out as `Result<U, E2>` when
+    /// `E2: From<E1>`.
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::type_complexity)]
+    fn with_filtered_into<R, O, F>(self, f: F) -> DefilteredIter<O, buffer::Buffer<R>>
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnMut(FilteredIter<Self, buffer::Buffer<R>, residual::ConvertResidual<R>>) -> O,
+        O: Iterator<Item = R::Output>,
+        T: Try,
+        R: Try + FromResidual<T::Residual>,
+    {
+        filter_with_map(
+            self,
+            buffer::Buffer::default(),
+            residual::ConvertResidual::new(),
+            f,
+        )
+    }
+
+    /// Like [`IterFiltered::with_filtered`], but the recombined items are
+    /// [`tagged::Item`]s: the chain's output comes out as `Value`s and the
+    /// residuals of divergent values as `Divergent`s. The chain can yield
+    /// anything, even the source's own `Try` type, without its output being
+    /// confused with what was passed through.
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::type_complexity)]
+    fn with_filtered_tagged<O, U, F>(
+        self,
+        f: F,
+    ) -> DefilteredIter<O, buffer::Buffer<tagged::Item<U, T::Residual>>>
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnMut(
+            FilteredIter<
+                Self,
+                buffer::Buffer<tagged::Item<U, T::Residual>>,
+                residual::TagResidual<U>,
+            >,
+        ) -> O,
+        O: Iterator<Item = U>,
+        T: Try,
+    {
+        filter_with_map(
+            self,
+            buffer::Buffer::default(),
+            residual::TagResidual::new(),
+            f,
+        )
+    }
+
+    /// Like [`IterFiltered::with_filtered`], for sources that can never
+    /// diverge, such as `Result<T, Infallible>`. With nothing to buffer, the
+    /// chain's output is just wrapped back up, and the whole thing is an
+    /// [`ExactSizeIterator`] (or double-ended) whenever the chain is.
+    fn with_infallible<O, U, F>(self, mut f: F) -> core::iter::Map<O, fn(U) -> T>
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnMut(InfallibleIter<Self>) -> O,
+        O: Iterator<Item = U>,
+        T: Try<Output = U>,
+        T::Residual: residual::Uninhabited,
+    {
+        f(InfallibleIter::new(self)).map(T::from_output)
+    }
+
+    /// Like [`IterFiltered::with_filtered_buf`], but rather than being
+    /// buffered for recombination, the residual of each divergent value is
+    /// given to `sink` as soon as it's found, and the chain `f` returns is
+    /// handed back as it is. So this gives only the chain's output, with the
+    /// divergent values going to the side.
+    fn with_filtered_sink<O, U, S, F>(self, sink: S, mut f: F) -> O
+    where
+        Self: Iterator<Item = T> + Sized,
+        S: FnMut(T::Residual),
+        F: FnMut(FilteredIter<Self, NoBuffer, residual::SinkResidual<S>>) -> O,
+        O: Iterator<Item = U>,
+        T: Try,
+    {
+        f(FilteredIter::with_map(
+            self,
+            NoBuffer,
+            residual::SinkResidual::new(sink),
+        ))
+    }
+
+    /// Starts a [`builder::FilteredBuilder`] over the `self` iterator, for
+    /// setting up a buffer, residual mapping and so on before running a
+    /// chain over it. With nothing set, `.filtered().run(f)` is the same as
+    /// [`IterFiltered::with_filtered`].
+    fn filtered(self) -> builder::FilteredBuilder<Self>
+    where
+        Self: Iterator<Item = T> + Sized,
+    {
+        builder::FilteredBuilder::new(self)
+    }
+
+    /// Runs through the whole source, calling `on_value` with each normal
+    /// value and `on_divergent` with the residual of each divergent one, in
+    /// the order they come. Nothing is buffered, so this doesn't allocate.
+    fn for_each_split<U, V, D>(self, on_value: V, on_divergent: D)
+    where
+        Self: Iterator<Item = T> + Sized,
+        T: Try<Output = U>,
+        V: FnMut(U),
+        D: FnMut(T::Residual),
+    {
+        self.with_filtered_sink(on_divergent, |i| i)
+            .for_each(on_value)
+    }
+
+    /// Like [`IterFiltered::with_filtered`], but collects the recombined
+    /// output into `C` and hands it back along with the buffer, so that
+    /// anything left in the buffer can be looked at afterwards.
+    ///
+    /// The buffer only holds items that were ready to come out but never
+    /// given to the collection, which happens when `C` stops taking items
+    /// early: collecting into `Result<Vec<_>, _>` stops at the first `Err`,
+    /// say, leaving any other `Err`s pulled along with it, followed by the
+    /// output of the chain that was waiting behind them. If the collection
+    /// takes everything it's given, the buffer is always empty.
+    #[cfg(feature = "alloc")]
+    fn with_filtered_collecting<C, O, U, F>(self, f: F) -> (C, buffer::Buffer<T>)
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnMut(FilteredIter<Self, buffer::Buffer<T>>) -> O,
+        O: Iterator<Item = U>,
+        T: Try<Output = U>,
+        C: FromIterator<T>,
+    {
+        let buffer = buffer::Buffer::default();
+        let output = self.with_filtered_buf(buffer.clone(), f).collect();
+        (output, buffer)
+    }
+
+    /// Like [`IterFiltered::with_filtered`] with a chain that just `map`s
+    /// each normal value through `f`. Since that gives exactly one output
+    /// per normal value, there's one recombined item per source item, so
+    /// the result is an [`ExactSizeIterator`] with the source's length.
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::type_complexity)]
+    fn with_filtered_exact<U, F>(
+        self,
+        f: F,
+    ) -> ExactLen<
+        DefilteredIter<
+            core::iter::Map<FilteredIter<Self, buffer::Buffer<T>>, F>,
+            buffer::Buffer<T>,
+        >,
+    >
+    where
+        Self: ExactSizeIterator<Item = T> + Sized,
+        F: FnMut(U) -> U,
+        T: Try<Output = U>,
+    {
+        let len = self.len();
+        let buffer = buffer::Buffer::default();
+        let output = FilteredIter::new(self, buffer.clone()).map(f);
+        ExactLen::new(DefilteredIter::new(output, buffer, Some(len)), len)
+    }
+
+    /// Like [`IterFiltered::with_filtered`], but holds every divergent value
+    /// back until the chain has run out, and only then gives them out, in
+    /// the order they were found. Good for processing everything first and
+    /// reporting all the errors after.
+    #[cfg(feature = "alloc")]
+    fn with_filtered_flush_at_end<O, U, F>(self, mut f: F) -> FlushedIter<O, buffer::Buffer<T>>
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnMut(FilteredIter<Self, buffer::Buffer<T>>) -> O,
+        O: Iterator<Item = U>,
+        T: Try<Output = U>,
+    {
+        let buffer = buffer::Buffer::default();
+        let output = f(FilteredIter::new(self, buffer.clone()));
+        FlushedIter::new(output, buffer)
+    }
+
+    /// Like [`IterFiltered::with_filtered`], but stops with `sentinel` if
+    /// more than `max_buffered` divergent values ever need buffering at
+    /// once, as a safety valve against input that's all (or mostly)
+    /// divergent. Once that happens, anything still buffered is dropped,
+    /// `sentinel` is the next and last item, and nothing more is pulled from
+    /// the source.
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::type_complexity)]
+    fn with_filtered_capped<O, U, F>(
+        self,
+        max_buffered: usize,
+        sentinel: T,
+        f: F,
+    ) -> CappedIter<DefilteredIter<O, buffer::CappedBuffer<T>>, T>
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnMut(FilteredIter<Self, buffer::CappedBuffer<T>>) -> O,
+        O: Iterator<Item = U>,
+        T: Try<Output = U>,
+    {
+        let buffer = buffer::CappedBuffer::new(max_buffered);
+        let output = self.with_filtered_buf(buffer.clone(), f);
+        CappedIter::new(output, buffer, sentinel)
+    }
+
+    /// Runs through the whole source, splitting it into the normal values
+    /// and the errors of the divergent ones (eg. the `E` of a `Result`),
+    /// each in the order they came. Nothing short-circuits, so this always
+    /// consumes everything.
+    #[cfg(feature = "alloc")]
+    fn drain_into<U>(self) -> (Vec<U>, Vec<<T::Residual as residual::ResidualError>::Error>)
+    where
+        Self: Iterator<Item = T> + Sized,
+        T: Try<Output = U>,
+        T::Residual: residual::ResidualError,
+    {
+        use residual::ResidualError;
+
+        let mut errors = Vec::new();
+        let output = self
+            .with_filtered_sink(|residual| errors.push(residual.into_error()), |i| i)
+            .collect();
+        (output, errors)
+    }
+
+    /// Like [`IterFiltered::with_filtered`], but borrows the iterator rather
+    /// than taking it, in the same way as [`Iterator::by_ref`]. Whatever the
+    /// chain in `f` doesn't pull from the source is left there to be used
+    /// afterwards.
+    #[cfg(feature = "alloc")]
+    fn with_filtered_by_ref<'a, O, U, F>(&'a mut self, f: F) -> DefilteredIter<O, buffer::Buffer<T>>
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnMut(FilteredIter<&'a mut Self, buffer::Buffer<T>>) -> O,
+        O: Iterator<Item = U>,
+        T: Try<Output = U>,
+    {
+        self.by_ref().with_filtered(f)
+    }
+
+    /// Like [`IterFiltered::with_filtered`], but every item is paired with
+    /// its position in the original source, counting both normal and
+    /// divergent values, so `f` sees `(index, value)` pairs and the returned
+    /// iterator yields `(index, item)` pairs.
+    ///
+    /// Divergent values always keep the index they had in the source. For the
+    /// normal values, the index is whatever the chain in `f` carries through:
+    /// values it filters out leave a gap, and values it adds get whatever
+    /// index it gives them, so the output indices are only guaranteed to be
+    /// increasing if the chain keeps them that way.
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::type_complexity)]
+    fn with_filtered_indexed<O, U, F>(
+        self,
+        f: F,
+    ) -> core::iter::Map<DefilteredIter<O, buffer::Buffer<Indexed<T>>>, fn(Indexed<T>) -> (usize, T)>
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnMut(FilteredIter<IndexedIter<Self>, buffer::Buffer<Indexed<T>>>) -> O,
+        O: Iterator<Item = (usize, U)>,
+        T: Try<Output = U>,
+    {
+        IndexedIter::new(self)
+            .with_filtered(f)
+            .map(Indexed::into_inner as fn(Indexed<T>) -> (usize, T))
+    }
+
+    /// Like [`IterFiltered::with_filtered_indexed`], but drops the indices
+    /// again once the items are recombined, checking with a debug assertion
+    /// that they came out in source order.
+    ///
+    /// Chains that carry the indices through unchanged and only filter,
+    /// map or expand in place (eg. `filter`, `map`, `flat_map`) always pass.
+    /// Chains that reorder the values, or look ahead the way `peekable`
+    /// does (see [`filtered::DefilteredIter`]), are caught in debug builds
+    /// rather than quietly putting divergent values in the wrong place.
+    #[cfg(feature = "alloc")]
+    #[allow(clippy::type_complexity)]
+    fn with_filtered_ordered<O, U, F>(
+        self,
+        f: F,
+    ) -> OrderChecked<
+        core::iter::Map<
+            DefilteredIter<O, buffer::Buffer<Indexed<T>>>,
+            fn(Indexed<T>) -> (usize, T),
+        >,
+    >
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnMut(FilteredIter<IndexedIter<Self>, buffer::Buffer<Indexed<T>>>) -> O,
+        O: Iterator<Item = (usize, U)>,
+        T: Try<Output = U>,
+    {
+        OrderChecked::new(self.with_filtered_indexed(f))
+    }
+
+    /// Splits the `self` iterator into two that share the buffer `buf`: one
+    /// of the 'normal' values, and one of the divergent values the first
+    /// has skipped over. See [`Partitioned`] for how they depend on each
+    /// other.
+    fn partition_filtered<B>(self, buf: B) -> Partitioned<Self, B>
+    where
+        Self: Iterator<Item = T> + Sized,
+        T: Try,
+        B: ControlFlowBuffer<Item = T>,
+    {
+        Partitioned::new(self, buf)
+    }
 }
 
 impl<I, T> IterFiltered<T> for I
@@ -129,18 +477,33 @@ where
     fn with_filtered_buf<B, O, U, F>(self, buffer: B, f: F) -> DefilteredIter<O, B>
     where
         Self: Iterator<Item = T> + Sized,
-        F: Fn(FilteredIter<Self, B>) -> O,
+        F: FnMut(FilteredIter<Self, B>) -> O,
         O: Iterator<Item = U>,
         T: Try<Output = U>,
         B: ControlFlowBuffer<Item = T>,
     {
-        let original_max = self.size_hint().1;
-        let buf_iter = FilteredIter::new(self, buffer.clone());
-
-        DefilteredIter::new(f(buf_iter), buffer, original_max)
+        filter_with_map(self, buffer, residual::FromResidualMap, f)
     }
 }
 
+fn filter_with_map<I, B, M, O, F>(iter: I, buffer: B, map: M, mut f: F) -> DefilteredIter<O, B>
+where
+    I: Iterator,
+    F: FnMut(FilteredIter<I, B, M>) -> O,
+    O: Iterator<Item = <B::Item as Try>::Output>,
+    B: ControlFlowBuffer,
+    B::Item: Try,
+{
+    let original_max = iter.size_hint().1;
+    // divergent values are usually a minority, so only reserve room for
+    // a fraction of them, and not so much that a huge source allocates
+    // a huge buffer up front.
+    buffer.reserve(original_max.map_or(0, |max| (max / 4).min(1024)));
+    let buf_iter = FilteredIter::with_map(iter, buffer.clone(), map);
+
+    DefilteredIter::new(f(buf_iter), buffer, original_max)
+}
+
 /// Including this trait adds the `with_folding` helper function to any normal 
 /// iterator.
 /// 
@@ -193,8 +556,320 @@ pub trait IterFolding<T> {
     fn with_folding<O, F, R>(self, f: F) -> T
     where
         Self: Iterator<Item = T> + Sized,
-        F: Fn(BreakingIterator<Self, T::Residual>) -> O,
+        F: FnOnce(BreakingIterator<Self, T::Residual>) -> O,
         T: Try<Output = O, Residual = R>;
+
+    /// Takes the `self` iterator and calls `f` with an iterator that will
+    /// provide all the 'normal' values, skipping over the divergent ones.
+    /// Returns the result of the function if there weren't any divergent
+    /// values, or all of their residuals in order if there were.
+    #[cfg(feature = "alloc")]
+    fn with_folding_all<O, F>(self, f: F) -> Result<O, Vec<T::Residual>>
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnOnce(CollectingIterator<Self, T::Residual>) -> O,
+        T: Try,
+    {
+        let mut divergents = Vec::new();
+        let output = f(CollectingIterator::new(self, &mut divergents));
+
+        if divergents.is_empty() {
+            Ok(output)
+        } else {
+            Err(divergents)
+        }
+    }
+
+    /// Like [`IterFolding::with_folding`], but borrows the iterator rather
+    /// than taking it, in the same way as [`Iterator::by_ref`]. Whatever `f`
+    /// doesn't pull from the source is left there to be used afterwards.
+    fn with_folding_by_ref<'a, O, F, R>(&'a mut self, f: F) -> T
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnOnce(BreakingIterator<&'a mut Self, T::Residual>) -> O,
+        T: Try<Output = O, Residual = R>,
+    {
+        self.by_ref().with_folding(f)
+    }
+
+    /// Like [`IterFolding::with_folding`], but also hands back the rest of
+    /// the `self` iterator, including anything `f` didn't get to because it
+    /// stopped pulling early. If a divergent value was found, the rest starts
+    /// just after it.
+    fn with_folding_remainder<O, F, R>(self, f: F) -> (T, Self)
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnOnce(BreakingIterator<&mut Self, T::Residual>) -> O,
+        T: Try<Output = O, Residual = R>,
+    {
+        let mut rest = self;
+        let mut result = None;
+        let output = f(BreakingIterator::new(&mut rest, &mut result));
+        match result {
+            Some(residual) => (T::from_residual(residual), rest),
+            None => (T::from_output(output), rest),
+        }
+    }
+
+    /// Like [`IterFolding::with_folding`], for sources that can never
+    /// diverge, such as `Result<T, Infallible>`. There's no break to record,
+    /// so `f` gets an [`InfallibleIter`], which just unwraps each item and
+    /// folds with the source's own `fold`.
+    ///
+    /// The compiler can usually see that an uninhabited residual never
+    /// breaks, so this isn't much faster than `with_folding` (the `folding`
+    /// bench compares them). What it adds is that `f`'s iterator keeps the
+    /// source's exact size, and is double-ended whenever the source is.
+    fn with_folding_infallible<O, F>(self, f: F) -> T
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnOnce(InfallibleIter<Self>) -> O,
+        T: Try<Output = O>,
+        T::Residual: residual::Uninhabited,
+    {
+        T::from_output(f(InfallibleIter::new(self)))
+    }
+
+    /// Like [`IterFolding::with_folding`], but also gives the number of items
+    /// pulled from the `self` iterator, including the divergent value that
+    /// stopped it, if there was one. To make that count available afterwards,
+    /// `f` is given the [`BreakingIterator`] by reference.
+    fn with_folding_counted<O, F, R>(self, f: F) -> (T, usize)
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnOnce(&mut BreakingIterator<Self, T::Residual>) -> O,
+        T: Try<Output = O, Residual = R>,
+    {
+        let mut result = None;
+        let mut iter = BreakingIterator::new(self, &mut result);
+        let output = f(&mut iter);
+        let consumed = iter.consumed();
+        match result {
+            Some(residual) => (T::from_residual(residual), consumed),
+            None => (T::from_output(output), consumed),
+        }
+    }
+
+    /// Like [`IterFolding::with_folding`], but `f` can also stop early with a
+    /// value of its own by returning [`ControlFlow::Break`], as with
+    /// [`Iterator::try_fold`]. A divergent value from the `self` iterator
+    /// takes precedence over that, even if `f` carries on and breaks after
+    /// seeing the input stop short.
+    fn with_folding_try<B, C, F>(self, f: F) -> TryFolded<B, C, T>
+    where
+        Self: Iterator<Item = T> + Sized,
+        F: FnOnce(BreakingIterator<Self, T::Residual>) -> ControlFlow<B, C>,
+        T: Try,
+    {
+        match fold_breaking(self, f) {
+            ControlFlow::Break(residual) => TryFolded::Diverged(T::from_residual(residual)),
+            ControlFlow::Continue(ControlFlow::Break(stopped)) => TryFolded::Stopped(stopped),
+            ControlFlow::Continue(ControlFlow::Continue(completed)) => {
+                TryFolded::Completed(completed)
+            }
+        }
+    }
+
+    /// Sums the 'normal' values of the `self` iterator, or returns the first
+    /// divergent value.
+    fn with_sum(self) -> T
+    where
+        Self: Iterator<Item = T> + Sized,
+        T: Try,
+        T::Output: core::iter::Sum,
+    {
+        self.with_folding(|i| i.sum())
+    }
+
+    /// Multiplies the 'normal' values of the `self` iterator, or returns the
+    /// first divergent value.
+    fn with_product(self) -> T
+    where
+        Self: Iterator<Item = T> + Sized,
+        T: Try,
+        T::Output: core::iter::Product,
+    {
+        self.with_folding(|i| i.product())
+    }
+
+    /// Returns the largest 'normal' value of the `self` iterator, or the first
+    /// divergent value. Like [`Iterator::max`], the value is `None` if the
+    /// iterator is empty, and as with [`IterFolding::with_count`], the
+    /// [`Try`] type to wrap it in has to be given (or inferred).
+    fn with_max<C>(self) -> C
+    where
+        Self: Iterator<Item = T> + Sized,
+        T: Try,
+        T::Output: Ord,
+        C: Try<Output = Option<T::Output>> + FromResidual<T::Residual>,
+    {
+        match fold_breaking(self, |i| i.max()) {
+            ControlFlow::Break(residual) => C::from_residual(residual),
+            ControlFlow::Continue(max) => C::from_output(max),
+        }
+    }
+
+    /// Returns the smallest 'normal' value of the `self` iterator, or the
+    /// first divergent value. As with [`IterFolding::with_max`], the value
+    /// is `None` if the iterator is empty, and the [`Try`] type to wrap it in
+    /// has to be given (or inferred).
+    fn with_min<C>(self) -> C
+    where
+        Self: Iterator<Item = T> + Sized,
+        T: Try,
+        T::Output: Ord,
+        C: Try<Output = Option<T::Output>> + FromResidual<T::Residual>,
+    {
+        match fold_breaking(self, |i| i.min()) {
+            ControlFlow::Break(residual) => C::from_residual(residual),
+            ControlFlow::Continue(min) => C::from_output(min),
+        }
+    }
+
+    /// Counts the 'normal' values of the `self` iterator, or returns the first
+    /// divergent value. As the count has a different type to the values, the
+    /// [`Try`] type to wrap it in has to be given (or inferred), much like
+    /// [`Iterator::collect`].
+    fn with_count<C>(self) -> C
+    where
+        Self: Iterator<Item = T> + Sized,
+        T: Try,
+        C: Try<Output = usize> + FromResidual<T::Residual>,
+    {
+        match fold_breaking(self, |i| i.count()) {
+            ControlFlow::Break(residual) => C::from_residual(residual),
+            ControlFlow::Continue(count) => C::from_output(count),
+        }
+    }
+
+    /// Collects the 'normal' values of the `self` iterator into `C`, or
+    /// returns the first divergent value, like collecting into a `Result`
+    /// or `Option` does. As with [`IterFolding::with_count`], the [`Try`]
+    /// type to wrap the collection in has to be given (or inferred), but it
+    /// can be any that the divergent values convert into.
+    fn with_collect<R, C>(self) -> R
+    where
+        Self: Iterator<Item = T> + Sized,
+        T: Try,
+        C: FromIterator<T::Output>,
+        R: Try<Output = C> + FromResidual<T::Residual>,
+    {
+        match fold_breaking(self, |i| i.collect()) {
+            ControlFlow::Break(residual) => R::from_residual(residual),
+            ControlFlow::Continue(collected) => R::from_output(collected),
+        }
+    }
+
+    /// Returns the last 'normal' value of the `self` iterator, or the first
+    /// divergent value. As with [`IterFolding::with_max`], the value is
+    /// `None` if the iterator is empty, and the [`Try`] type to wrap it in
+    /// has to be given (or inferred).
+    fn with_last<C>(self) -> C
+    where
+        Self: Iterator<Item = T> + Sized,
+        T: Try,
+        C: Try<Output = Option<T::Output>> + FromResidual<T::Residual>,
+    {
+        match fold_breaking(self, |i| i.last()) {
+            ControlFlow::Break(residual) => C::from_residual(residual),
+            ControlFlow::Continue(last) => C::from_output(last),
+        }
+    }
+
+    /// Calls `f` with each 'normal' value of the `self` iterator, stopping at
+    /// the first divergent value and returning it. As with
+    /// [`IterFolding::with_count`], the [`Try`] type to return has to be given
+    /// (or inferred).
+    fn with_try_for_each<C, F>(self, f: F) -> C
+    where
+        Self: Iterator<Item = T> + Sized,
+        T: Try,
+        C: Try<Output = ()> + FromResidual<T::Residual>,
+        F: FnMut(T::Output),
+    {
+        match fold_breaking(self, |i| i.for_each(f)) {
+            ControlFlow::Break(residual) => C::from_residual(residual),
+            ControlFlow::Continue(()) => C::from_output(()),
+        }
+    }
+
+    /// Returns the first 'normal' value of the `self` iterator that matches
+    /// `predicate`, or the first divergent value if one comes before it. As
+    /// with [`IterFolding::with_count`], the [`Try`] type to return has to be
+    /// given (or inferred).
+    fn with_find<C, P>(self, predicate: P) -> C
+    where
+        Self: Iterator<Item = T> + Sized,
+        T: Try,
+        C: Try<Output = Option<T::Output>> + FromResidual<T::Residual>,
+        P: FnMut(&T::Output) -> bool,
+    {
+        match fold_breaking(self, |mut i| i.find(predicate)) {
+            ControlFlow::Break(residual) => C::from_residual(residual),
+            ControlFlow::Continue(found) => C::from_output(found),
+        }
+    }
+
+    /// Returns the first non-`None` result of `f` on the 'normal' values of
+    /// the `self` iterator, or the first divergent value if one comes before
+    /// it. As with [`IterFolding::with_count`], the [`Try`] type to return has
+    /// to be given (or inferred).
+    fn with_find_map<C, U, F>(self, f: F) -> C
+    where
+        Self: Iterator<Item = T> + Sized,
+        T: Try,
+        C: Try<Output = Option<U>> + FromResidual<T::Residual>,
+        F: FnMut(T::Output) -> Option<U>,
+    {
+        match fold_breaking(self, |mut i| i.find_map(f)) {
+            ControlFlow::Break(residual) => C::from_residual(residual),
+            ControlFlow::Continue(found) => C::from_output(found),
+        }
+    }
+
+    /// Folds the 'normal' values of the `self` iterator into an accumulator
+    /// starting at `init`, where `f` can also bail out by returning a
+    /// residual. Returns the first divergent value, whether from the
+    /// iterator or from `f`, or the final accumulator. As with
+    /// [`IterFolding::with_count`], the [`Try`] type to wrap the accumulator
+    /// in has to be given (or inferred).
+    fn with_folding_scan<A, C, F>(self, init: A, mut f: F) -> C
+    where
+        Self: Iterator<Item = T> + Sized,
+        T: Try,
+        C: Try<Output = A> + FromResidual<T::Residual>,
+        F: FnMut(&mut A, T::Output) -> ControlFlow<T::Residual>,
+    {
+        let scanned = fold_breaking(self, |mut iter| {
+            let mut acc = init;
+            while let Some(next) = iter.next() {
+                if let ControlFlow::Break(residual) = f(&mut acc, next) {
+                    iter.break_with(residual);
+                }
+            }
+            acc
+        });
+        match scanned {
+            ControlFlow::Break(residual) => C::from_residual(residual),
+            ControlFlow::Continue(acc) => C::from_output(acc),
+        }
+    }
+}
+
+/// Calls `f` with a [`BreakingIterator`] over `iter`, returning the first
+/// residual it found if there was one, or the result of `f` otherwise.
+fn fold_breaking<I, T, O, F>(iter: I, f: F) -> ControlFlow<T::Residual, O>
+where
+    I: Iterator<Item = T>,
+    T: Try,
+    F: FnOnce(BreakingIterator<I, T::Residual>) -> O,
+{
+    let mut result = None;
+    let output = f(BreakingIterator::new(iter, &mut result));
+    match result {
+        Some(residual) => ControlFlow::Break(residual),
+        None => ControlFlow::Continue(output),
+    }
 }
 
 impl<I, T> IterFolding<T> for I
@@ -204,7 +879,7 @@ where
     fn with_folding<O, F, R>(self, f: F) -> T
     where
         Self: Iterator<Item = T> + Sized,
-        F: Fn(BreakingIterator<Self, T::Residual>) -> O,
+        F: FnOnce(BreakingIterator<Self, T::Residual>) -> O,
         T: Try<Output = O, Residual = R>,
     {
         let mut result = None;
@@ -221,10 +896,36 @@ where
     }
 }
 
+/// Including this trait adds the `with_folding_ref` helper function to any
+/// [`Peekable`](core::iter::Peekable) iterator.
+pub trait PeekableFolding<I: Iterator> {
+    /// Like [`IterFolding::with_folding_by_ref`], but `f` only gets the
+    /// 'normal' values up to the first divergent one, which is left on the
+    /// source rather than taken, so the fold always returns its output (as
+    /// a `T`) and the divergent value comes next from the source.
+    ///
+    /// Telling whether the next value is divergent without taking it means
+    /// branching on a clone of it, so the items have to be `Clone`.
+    fn with_folding_ref<O, F>(&mut self, f: F) -> I::Item
+    where
+        F: FnOnce(NormalPrefix<'_, I>) -> O,
+        I::Item: Try<Output = O> + Clone;
+}
+
+impl<I: Iterator> PeekableFolding<I> for core::iter::Peekable<I> {
+    fn with_folding_ref<O, F>(&mut self, f: F) -> I::Item
+    where
+        F: FnOnce(NormalPrefix<'_, I>) -> O,
+        I::Item: Try<Output = O> + Clone,
+    {
+        Try::from_output(f(NormalPrefix::new(self)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use filtered::tests::result_samples;
+    use filtered::tests::{option_samples, result_samples};
 
     #[cfg(feature = "std")]
     #[test]
@@ -239,17 +940,1327 @@ mod tests {
         assert_eq!(output.next(), None);
     }
 
+    // no std here, so this checks the default buffer only needs alloc.
+    #[cfg(feature = "alloc")]
     #[test]
-    fn folded() {
-        result_samples()
-            .with_folding(|i| i.sum::<u32>())
-            .expect_err("foldable sum of sample data should error");
+    fn filtered_with_alloc() {
+        let output: Vec<_> = result_samples()
+            .with_filtered(|i| i.filter(|n| *n > 2))
+            .collect();
+        assert_eq!(output, [Err("boom"), Err("hi"), Ok(3), Err("zoop"), Ok(5)]);
+
+        let (output, errors) = result_samples().drain_into();
+        assert_eq!(output, [1, 2, 1, 3, 1, 5]);
+        assert_eq!(errors, ["boom", "hi", "zoop"]);
+    }
+
+    #[test]
+    fn filtered_stack_buffer() {
+        let buf = buffer::ArrayBuffer::<_, 4>::new();
+        let mut output = result_samples().with_filtered_buf(&buf, |i| i.filter(|n| *n > 2));
+
+        assert_eq!(output.next(), Some(Err("boom")));
+        assert_eq!(output.next(), Some(Err("hi")));
+        assert_eq!(output.next(), Some(Ok(3)));
+        assert_eq!(output.next(), Some(Err("zoop")));
+        assert_eq!(output.next(), Some(Ok(5)));
+        assert_eq!(output.next(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_into() {
+        let output: Vec<Result<u64, String>> = result_samples()
+            .with_filtered_into(|i| i.map(|n| u64::from(n) * 10))
+            .collect();
+
         assert_eq!(
-            result_samples()
-                .take(3)
-                .with_folding(|i| i.sum::<u32>())
-                .unwrap(),
-            4
+            output,
+            [
+                Ok(10),
+                Ok(20),
+                Ok(10),
+                Err("boom".to_owned()),
+                Err("hi".to_owned()),
+                Ok(30),
+                Ok(10),
+                Err("zoop".to_owned()),
+                Ok(50),
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_reordering_chain() {
+        // sorting pulls the whole source before yielding anything, so the
+        // divergent values all come out first.
+        let output: Vec<_> = result_samples()
+            .with_filtered(|i| {
+                let mut sorted: Vec<_> = i.collect();
+                sorted.sort_unstable();
+                sorted.into_iter()
+            })
+            .collect();
+
+        assert_eq!(
+            output,
+            [
+                Err("boom"),
+                Err("hi"),
+                Err("zoop"),
+                Ok(1),
+                Ok(1),
+                Ok(1),
+                Ok(2),
+                Ok(3),
+                Ok(5),
+            ]
         );
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_ordered() {
+        let output: Vec<_> = result_samples()
+            .with_filtered_ordered(|i| {
+                i.filter(|(_, n)| *n > 1)
+                    .flat_map(|(idx, n)| [(idx, n), (idx, n * 10)])
+            })
+            .collect();
+
+        assert_eq!(
+            output,
+            [
+                Ok(2),
+                Ok(20),
+                Err("boom"),
+                Err("hi"),
+                Ok(3),
+                Ok(30),
+                Err("zoop"),
+                Ok(5),
+                Ok(50),
+            ]
+        );
+    }
+
+    #[cfg(all(feature = "std", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "came out after index")]
+    fn filtered_ordered_catches_reordering() {
+        result_samples()
+            .with_filtered_ordered(|i| {
+                let mut sorted: Vec<_> = i.collect();
+                sorted.sort_unstable_by_key(|(_, n)| *n);
+                sorted.into_iter()
+            })
+            .for_each(drop);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_collecting() {
+        // collecting into a Result stops at "boom", which was pulled along
+        // with "hi" on the way to 3.
+        let (output, buf): (Result<Vec<u32>, &str>, _) =
+            result_samples().with_filtered_collecting(|i| i.map(|n| n * 2));
+
+        assert_eq!(output, Err("boom"));
+        assert_eq!(buf.drain().collect::<Vec<_>>(), [Err("hi"), Ok(6)]);
+
+        let (output, buf): (Vec<_>, _) =
+            result_samples().with_filtered_collecting(|i| i.map(|n| n * 2));
+
+        assert_eq!(output.len(), 9);
+        assert!(buf.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_sink() {
+        let mut errors = Vec::new();
+        let output: Vec<_> = result_samples()
+            .with_filtered_sink(|err| errors.push(err), |i| i.map(|n| n * 10))
+            .collect();
+
+        assert_eq!(output, [10, 20, 10, 30, 10, 50]);
+        assert_eq!(errors, [Err("boom"), Err("hi"), Err("zoop")]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_builder() {
+        let direct: Vec<_> = result_samples()
+            .with_filtered(|i| i.filter(|n| *n > 1))
+            .collect();
+        let built: Vec<_> = result_samples()
+            .filtered()
+            .errors_first()
+            .run(|i| i.filter(|n| *n > 1))
+            .collect();
+        assert_eq!(built, direct);
+
+        let direct: Vec<_> = result_samples()
+            .with_filtered_buf(BoundedBuffer::new(1, Err("full")), |i| i.map(|n| n * 10))
+            .collect();
+        let built: Vec<_> = result_samples()
+            .filtered()
+            .buffer(BoundedBuffer::new(1, Err("full")))
+            .run(|i| i.map(|n| n * 10))
+            .collect();
+        assert_eq!(built, direct);
+
+        // the buffer follows the type the residuals are mapped to.
+        let direct: Vec<_> = result_samples()
+            .with_filtered(|i| i.map(|n| n * 10))
+            .map(|item| item.map_err(|err| err.len()))
+            .collect();
+        let built: Vec<Result<u32, usize>> = result_samples()
+            .filtered()
+            .map_residual(|err: Result<u32, &str>| err.map_err(|err| err.len()))
+            .run(|i| i.map(|n| n * 10))
+            .collect();
+        assert_eq!(built, direct);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_for_each_split() {
+        let mut values = Vec::new();
+        let mut errors = Vec::new();
+        result_samples().for_each_split(|n| values.push(n), |err| errors.push(err));
+
+        assert_eq!(values, [1, 2, 1, 3, 1, 5]);
+        assert_eq!(errors, [Err("boom"), Err("hi"), Err("zoop")]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_named_fn() {
+        fn doubled<I, B, T>(normal: FilteredIter<I, B>) -> impl Iterator<Item = T::Output>
+        where
+            I: Iterator<Item = T>,
+            T: Try,
+            T::Output: Clone,
+            B: ControlFlowBuffer<Item = T>,
+        {
+            normal.flat_map(|n| [n.clone(), n])
+        }
+
+        let output: Vec<_> = result_samples().take(4).with_filtered(doubled).collect();
+        assert_eq!(
+            output,
+            [Ok(1), Ok(1), Ok(2), Ok(2), Ok(1), Ok(1), Err("boom")]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_step_by() {
+        // step_by(2) keeps the 1st, 3rd and 5th normal values (all 1s). The
+        // errors it pulls while skipping 3 and 5 come out before whatever it
+        // yields next, or at the end.
+        let output: Vec<_> = result_samples().with_filtered(|i| i.step_by(2)).collect();
+
+        assert_eq!(
+            output,
+            [Ok(1), Ok(1), Err("boom"), Err("hi"), Ok(1), Err("zoop")]
+        );
+
+        // either side of a dropped value comes out in the same place.
+        for source in [
+            [Ok(1), Ok(2), Err("e"), Ok(3), Ok(4)],
+            [Ok(1), Err("e"), Ok(2), Ok(3), Ok(4)],
+        ] {
+            let output: Vec<_> = source.into_iter().with_filtered(|i| i.step_by(2)).collect();
+            assert_eq!(output, [Ok(1), Err("e"), Ok(3)]);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_errors_first() {
+        // Ok(3) is what triggers the pull past "boom" and "hi", so both of
+        // them come out ahead of it, even after it's been mapped.
+        let output: Vec<_> = result_samples()
+            .skip(2)
+            .with_filtered(|i| i.map(|n| n * 10))
+            .collect();
+
+        assert_eq!(
+            output,
+            [
+                Ok(10),
+                Err("boom"),
+                Err("hi"),
+                Ok(30),
+                Ok(10),
+                Err("zoop"),
+                Ok(50)
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_flat_map() {
+        let output: Vec<_> = result_samples()
+            .with_filtered(|i| i.flat_map(|n| [n, n]))
+            .collect();
+
+        assert_eq!(
+            output,
+            [
+                Ok(1),
+                Ok(1),
+                Ok(2),
+                Ok(2),
+                Ok(1),
+                Ok(1),
+                Err("boom"),
+                Err("hi"),
+                Ok(3),
+                Ok(3),
+                Ok(1),
+                Ok(1),
+                Err("zoop"),
+                Ok(5),
+                Ok(5)
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_take() {
+        let mut source = result_samples();
+        let output: Vec<_> = source.with_filtered_by_ref(|i| i.take(2)).collect();
+        assert_eq!(output, [Ok(1), Ok(2)]);
+        assert_eq!(source.next(), Some(Ok(1)));
+
+        // the divergent values pulled on the way to the last item come out
+        // before it, but the ones after it are never pulled.
+        let mut source = result_samples();
+        let output: Vec<_> = source.with_filtered_by_ref(|i| i.take(4)).collect();
+        assert_eq!(output, [Ok(1), Ok(2), Ok(1), Err("boom"), Err("hi"), Ok(3)]);
+        assert_eq!(source.next(), Some(Ok(1)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_try_map() {
+        let output: Vec<_> = result_samples()
+            .with_filtered(|i| {
+                i.try_map(|n| if n == 3 { Err("three") } else { Ok(n * 10) })
+                    .filter(|n| *n > 10)
+            })
+            .collect();
+
+        assert_eq!(
+            output,
+            [
+                Ok(20),
+                Err("boom"),
+                Err("hi"),
+                Err("three"),
+                Err("zoop"),
+                Ok(50)
+            ]
+        );
+    }
+
+    #[test]
+    fn filtered_infallible() {
+        fn exact_len(iter: impl ExactSizeIterator) -> usize {
+            iter.len()
+        }
+
+        let source = || {
+            [1, 2, 3, 4]
+                .into_iter()
+                .map(Ok::<u32, core::convert::Infallible>)
+        };
+        let output = source().with_infallible(|i| i.map(|n| n * 2).skip(1));
+        assert_eq!(exact_len(output), 3);
+
+        let mut output = source().with_infallible(|i| i.map(|n| n * 2).skip(1));
+        assert_eq!(output.next_back(), Some(Ok(8)));
+        assert_eq!(output.len(), 2);
+        assert_eq!(output.next(), Some(Ok(4)));
+        assert_eq!(output.next(), Some(Ok(6)));
+        assert_eq!(output.next(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_is_lazy() {
+        let source = core::iter::from_fn(|| -> Option<Result<u32, &str>> {
+            panic!("pulled from the source")
+        });
+        let output = source.with_filtered(|i| i.map(|n| n * 2).filter(|n| *n > 3));
+        assert_eq!(output.size_hint(), (0, None));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_tagged() {
+        use tagged::Item;
+
+        // the chain yields Results of its own, which stay apart from the
+        // errors passed through from the source.
+        let output: Vec<_> = result_samples()
+            .with_filtered_tagged(|i| i.map(|n| if n > 2 { Err(n) } else { Ok(n) }))
+            .collect();
+
+        let mut values = Vec::new();
+        let mut passed = Vec::new();
+        for item in output {
+            match item {
+                Item::Value(Ok(n)) => values.push(n),
+                Item::Value(Err(n)) => values.push(n * 100),
+                Item::Divergent(residual) => passed.push(Result::<(), _>::from_residual(residual)),
+            }
+        }
+        assert_eq!(values, [1, 2, 1, 300, 1, 500]);
+        assert_eq!(passed, [Err("boom"), Err("hi"), Err("zoop")]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_seeded_buffer() {
+        let buf = Buffer::from_items([Err("restored"), Err("again")]);
+        assert_eq!(buf.len(), 2);
+        assert_eq!(buf.divergents_seen(), Some(0));
+
+        let output: Vec<_> = result_samples()
+            .take(4)
+            .with_filtered_buf(buf.clone(), |i| i.map(|n| n * 10))
+            .collect();
+        assert_eq!(
+            output,
+            [
+                Err("restored"),
+                Err("again"),
+                Ok(10),
+                Ok(20),
+                Ok(10),
+                Err("boom"),
+            ]
+        );
+
+        buf.extend_back([Err("later")]);
+        assert_eq!(buf.drain().collect::<Vec<_>>(), [Err("later")]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_borrowed_outputs() {
+        let text = String::from("007 42 x 0 yy 13");
+        let words = || {
+            text.split(' ').map(|word| {
+                if word.bytes().all(|b| b.is_ascii_digit()) {
+                    Ok(word)
+                } else {
+                    Err(word.len())
+                }
+            })
+        };
+
+        // the outputs are slices of `text`, all the way through.
+        let output: Vec<Result<&str, usize>> = words()
+            .with_filtered(|i| i.map(|word| word.trim_start_matches('0')))
+            .collect();
+        assert_eq!(
+            output,
+            [Ok("7"), Ok("42"), Err(1), Ok(""), Err(2), Ok("13")]
+        );
+
+        let lengths: std::collections::HashMap<_, _> = [("a", 1), ("bb", 2)].into_iter().collect();
+        let output: Vec<Option<&u32>> = ["a", "c", "bb"]
+            .iter()
+            .map(|key| lengths.get(key))
+            .with_filtered(|i| i.filter(|n| **n > 1))
+            .collect();
+        assert_eq!(output, [None, Some(&2)]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_interleaved() {
+        let a = result_samples().with_filtered(|i| i.filter(|n| *n > 1));
+        let b = [Err("b1"), Ok(10), Ok(20)]
+            .into_iter()
+            .with_filtered(|i| i.map(|n| n + 1));
+        let output = interleave_filtered(a, b);
+        assert_eq!(output.size_hint(), (0, Some(12)));
+
+        assert_eq!(
+            output.collect::<Vec<_>>(),
+            [
+                Ok(2),
+                Err("b1"),
+                Err("boom"),
+                Ok(11),
+                Err("hi"),
+                Ok(21),
+                // b is done, so the rest of a follows on its own.
+                Ok(3),
+                Err("zoop"),
+                Ok(5),
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_chained_sources() {
+        let a = [Ok(1), Err("a1"), Err("a2")];
+        let b = [Err("b1"), Ok(2), Err("b2")];
+
+        let output = a.into_iter().chain(b).with_filtered(|i| i.map(|n| n * 10));
+        assert_eq!(output.size_hint(), (0, Some(6)));
+        assert_eq!(
+            output.collect::<Vec<_>>(),
+            [Ok(10), Err("a1"), Err("a2"), Err("b1"), Ok(20), Err("b2")]
+        );
+
+        // dropping the values either side of the join doesn't move the
+        // divergent values around it.
+        let output: Vec<_> = a
+            .into_iter()
+            .chain(b)
+            .with_filtered(|i| i.filter(|_| false))
+            .collect();
+        assert_eq!(output, [Err("a1"), Err("a2"), Err("b1"), Err("b2")]);
+
+        let output: Vec<_> = a
+            .into_iter()
+            .chain(b)
+            .with_filtered(|i| i.map(|n| n * 10))
+            .rev()
+            .collect();
+        assert_eq!(
+            output,
+            [Err("b2"), Ok(20), Err("b1"), Err("a2"), Err("a1"), Ok(10)]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_exact() {
+        let mut output = result_samples().with_filtered_exact(|n| n);
+        assert_eq!(output.size_hint(), (9, Some(9)));
+        assert_eq!(output.nth(3), Some(Err("boom")));
+        assert_eq!(output.len(), 5);
+
+        let output = result_samples().with_filtered_exact(|n| n * 10);
+        assert_eq!(output.rev().len(), 9);
+        let output: Vec<_> = result_samples().with_filtered_exact(|n| n).collect();
+        assert_eq!(output, result_samples().collect::<Vec<_>>());
+
+        // the length holds up however the divergent values are spread out.
+        let mut output = [Ok(0), Err(1), Err(2), Ok(3), Ok(4), Err(5), Ok(6)]
+            .into_iter()
+            .with_filtered_exact(|n| n * 10);
+        for left in (0..7).rev() {
+            assert!(output.next().is_some());
+            assert_eq!(output.len(), left);
+        }
+        assert_eq!(output.next(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_flush_at_end() {
+        let mut output = result_samples().with_filtered_flush_at_end(|i| i.map(|n| n * 10));
+        assert_eq!(output.size_hint(), (0, None));
+        assert_eq!(
+            output.by_ref().take(6).collect::<Vec<_>>(),
+            [Ok(10), Ok(20), Ok(10), Ok(30), Ok(10), Ok(50)]
+        );
+        assert_eq!(output.size_hint(), (3, Some(3)));
+        assert_eq!(
+            output.collect::<Vec<_>>(),
+            [Err("boom"), Err("hi"), Err("zoop")]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_capped() {
+        let mut source = [Ok(1), Err("a"), Ok(2)]
+            .into_iter()
+            .chain(core::iter::repeat(Err("flood")).take(1000))
+            .chain([Ok(3)]);
+        let output: Vec<_> = (&mut source)
+            .with_filtered_capped(10, Err("too many errors"), |i| i.map(|n| n * 10))
+            .collect();
+
+        assert_eq!(output, [Ok(10), Err("a"), Ok(20), Err("too many errors")]);
+        // it stopped at the 11th error in a row.
+        assert_eq!(source.count(), 1000 - 11 + 1);
+
+        // under the cap, nothing changes.
+        let output: Vec<_> = result_samples()
+            .with_filtered_capped(2, Err("too many errors"), |i| i)
+            .collect();
+        assert_eq!(output, result_samples().collect::<Vec<_>>());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_drain_into() {
+        let (output, errors) = result_samples().drain_into();
+        assert_eq!(output, [1, 2, 1, 3, 1, 5]);
+        assert_eq!(errors, ["boom", "hi", "zoop"]);
+
+        let (output, errors) = option_samples().drain_into();
+        assert_eq!(output, [1, 2, 1, 3, 1, 5]);
+        assert_eq!(errors, [(), (), ()]);
+
+        let (output, errors) = [ControlFlow::Continue(1), ControlFlow::Break("stop")]
+            .into_iter()
+            .drain_into();
+        assert_eq!(output, [1]);
+        assert_eq!(errors, ["stop"]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_into_result_vec() {
+        let output: Result<Vec<u32>, &str> =
+            result_samples().with_filtered(|i| i).into_result_vec();
+        assert_eq!(output, Err("boom"));
+
+        let output: Result<Vec<u32>, &str> = result_samples()
+            .take(3)
+            .with_filtered(|i| i.map(|n| n * 10))
+            .into_result_vec();
+        assert_eq!(output, Ok(vec![10, 20, 10]));
+
+        // whatever was already buffered is taken into account.
+        let buf = Buffer::from_items([Some(7)]);
+        let output: Option<Vec<u32>> = option_samples()
+            .with_filtered_buf(buf, |i| i)
+            .into_result_vec();
+        assert_eq!(output, None);
+        let buf = Buffer::from_items([Some(7)]);
+        let output: Option<Vec<u32>> = [Some(1)]
+            .into_iter()
+            .with_filtered_buf(buf, |i| i)
+            .into_result_vec();
+        assert_eq!(output, Some(vec![7, 1]));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_extend_until_err() {
+        let mut target = Vec::new();
+        let mut output = result_samples().with_filtered(|i| i);
+        let result: Result<(), &str> = output.extend_until_err(&mut target);
+        assert_eq!(result, Err("boom"));
+        assert_eq!(target, [1, 2, 1]);
+
+        // the rest is left to carry on with.
+        let result: Result<(), &str> = output.extend_until_err(&mut target);
+        assert_eq!(result, Err("hi"));
+        assert_eq!(target, [1, 2, 1]);
+        let result: Result<(), &str> = output.extend_until_err(&mut target);
+        assert_eq!(result, Err("zoop"));
+        assert_eq!(target, [1, 2, 1, 3, 1]);
+        let result: Result<(), &str> = output.extend_until_err(&mut target);
+        assert_eq!(result, Ok(()));
+        assert_eq!(target, [1, 2, 1, 3, 1, 5]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_replay_buffer() {
+        let mut source = result_samples();
+        let mut output = source.with_filtered_by_ref(|i| i.filter(|n| *n > 1));
+        assert_eq!(output.next(), Some(Ok(2)));
+        assert_eq!(output.next(), Some(Err("boom")));
+
+        // "hi" was pulled along with "boom", and the Ok(3) that came out of
+        // the chain right behind them is left out.
+        let replayed: Vec<_> = output.replay_buffer().collect();
+        assert_eq!(replayed, [Err("hi")]);
+        assert_eq!(source.next(), Some(Ok(1)));
+
+        // normal values queued between divergent ones are skipped too.
+        let buf = Buffer::from_items([Err("a"), Ok(1), Err("b"), Ok(2)]);
+        let output = [Ok(3)].into_iter().with_filtered_buf(buf, |i| i);
+        let replayed: Vec<_> = output.replay_buffer().collect();
+        assert_eq!(replayed, [Err("a"), Err("b")]);
+
+        let output = result_samples().with_filtered(|i| i);
+        assert_eq!(output.replay_buffer().count(), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_nth() {
+        let mut output = result_samples().with_filtered(|i| i);
+        assert_eq!(output.nth(3), Some(Err("boom")));
+        assert_eq!(output.nth(1), Some(Ok(3)));
+        assert_eq!(output.nth(3), None);
+
+        // skipping matches stepping through one at a time, starting from
+        // anywhere in a run of divergent values.
+        let chain = |i: FilteredIter<_, _>| i.filter(|n| *n != 3).map(|n| n * 10);
+        let expected: Vec<_> = result_samples().with_filtered(chain).collect();
+        for first in 0..expected.len() {
+            for n in 0..=expected.len() {
+                let mut output = result_samples().with_filtered(chain);
+                let mut reference = expected.iter().cloned();
+                assert_eq!(output.nth(first), reference.nth(first));
+                assert_eq!(output.nth(n), reference.nth(n));
+                assert_eq!(output.collect::<Vec<_>>(), reference.collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_last() {
+        assert_eq!(result_samples().with_filtered(|i| i).last(), Some(Ok(5)));
+        assert_eq!(
+            result_samples()
+                .with_filtered(|i| i.filter(|n| *n < 5))
+                .last(),
+            Some(Err("zoop"))
+        );
+        assert_eq!(
+            [Ok(1), Err("a"), Ok(2), Err("b"), Err("c")]
+                .into_iter()
+                .with_filtered(|i| i.map(|n| n * 10))
+                .last(),
+            Some(Err("c"))
+        );
+        assert_eq!(
+            Vec::<Result<u32, &str>>::new()
+                .into_iter()
+                .with_filtered(|i| i)
+                .last(),
+            None
+        );
+
+        // saturating a bounded buffer ends the output with its sentinel,
+        // just as when pulling one at a time.
+        let source = || [Err("a"), Err("b"), Err("c"), Ok(1), Err("d")].into_iter();
+        let buf = BoundedBuffer::new(2, Err("full"));
+        let output = source().with_filtered_buf(buf, |i| i.map(|n| n * 10));
+        assert_eq!(output.last(), Some(Err("full")));
+        let buf = BoundedBuffer::new(4, Err("full"));
+        let output = source()
+            .take(4)
+            .with_filtered_buf(buf, |i| i.map(|n| n * 10));
+        assert_eq!(output.last(), Some(Ok(10)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_mut_ref() {
+        let mut source = result_samples().collect::<Vec<_>>().into_iter();
+        {
+            let mut output = (&mut source).with_filtered(|i| i.map(|n| n * 10));
+            assert_eq!(output.size_hint(), (0, Some(9)));
+            assert_eq!(output.next(), Some(Ok(10)));
+            assert_eq!(output.next(), Some(Ok(20)));
+            assert_eq!(output.next(), Some(Ok(10)));
+            assert_eq!(output.next(), Some(Err("boom")));
+        }
+
+        // getting to "boom" meant pulling on through "hi" to Ok(3), which
+        // were both dropped with the pipeline, but the rest is still there.
+        assert_eq!(source.len(), 3);
+        assert_eq!(source.next(), Some(Ok(1)));
+        let rest: Vec<_> = (&mut source).with_filtered(|i| i.map(|n| n * 10)).collect();
+        assert_eq!(rest, [Err("zoop"), Ok(50)]);
+        assert_eq!(source.next(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_peekable() {
+        let output: Vec<_> = result_samples()
+            .with_filtered(|i| {
+                let mut i = i.peekable();
+                assert_eq!(i.peek(), Some(&1));
+                assert_eq!(i.next_if(|n| *n == 2), None);
+                i
+            })
+            .collect();
+        assert_eq!(output, result_samples().collect::<Vec<_>>());
+
+        // peeking past divergent values buffers them on the spot.
+        let mut hint = None;
+        let output: Vec<_> = result_samples()
+            .with_filtered(|i| {
+                let mut i = i.peekable();
+                assert_eq!(i.by_ref().take(3).count(), 3);
+                assert_eq!(i.peek(), Some(&3));
+                hint = Some(i.size_hint());
+                i
+            })
+            .collect();
+        assert_eq!(hint, Some((1, Some(4))));
+        assert_eq!(
+            output,
+            [Err("boom"), Err("hi"), Ok(3), Ok(1), Err("zoop"), Ok(5)]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_take_while() {
+        // Ok(3) is pulled (and dropped) to find the cutoff, so "boom" and
+        // "hi" from just before it still come out, after the last 1.
+        let mut source = result_samples();
+        let output: Vec<_> = source
+            .with_filtered_by_ref(|i| i.take_while(|n| *n < 3))
+            .collect();
+        assert_eq!(output, [Ok(1), Ok(2), Ok(1), Err("boom"), Err("hi")]);
+        assert_eq!(source.next(), Some(Ok(1)));
+
+        // the same goes for a cutoff right after a divergent value.
+        let output: Vec<_> = [Ok(1), Err("e"), Ok(5), Err("f")]
+            .into_iter()
+            .with_filtered(|i| i.take_while(|n| *n < 3))
+            .collect();
+        assert_eq!(output, [Ok(1), Err("e")]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_skip_while() {
+        // the errors pulled while skipping come out ahead of the first
+        // value it yields.
+        let output: Vec<_> = result_samples()
+            .with_filtered(|i| i.skip_while(|n| *n < 3))
+            .collect();
+        assert_eq!(
+            output,
+            [Err("boom"), Err("hi"), Ok(3), Ok(1), Err("zoop"), Ok(5)]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_options() {
+        let output: Vec<_> = option_samples()
+            .with_filtered(|i| i.filter(|n| *n > 2))
+            .collect();
+
+        assert_eq!(output, [None, None, Some(3), None, Some(5)]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_control_flow() {
+        let output: Vec<_> = result_samples()
+            .map(|i| match i {
+                Ok(n) => ControlFlow::Continue(n),
+                Err(err) => ControlFlow::Break(err),
+            })
+            .with_filtered(|i| i.filter(|n| *n > 2))
+            .collect();
+
+        assert_eq!(
+            output,
+            [
+                ControlFlow::Break("boom"),
+                ControlFlow::Break("hi"),
+                ControlFlow::Continue(3),
+                ControlFlow::Break("zoop"),
+                ControlFlow::Continue(5)
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_fnmut() {
+        let mut setups = 0;
+        let output: Vec<_> = result_samples()
+            .with_filtered(|i| {
+                setups += 1;
+                i.filter(|n| *n > 2)
+            })
+            .collect();
+
+        assert_eq!(setups, 1);
+        assert_eq!(output, [Err("boom"), Err("hi"), Ok(3), Err("zoop"), Ok(5)]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_by_ref() {
+        let mut source = result_samples();
+        let head: Vec<_> = source.with_filtered_by_ref(|i| i.take(2)).collect();
+
+        assert_eq!(head, [Ok(1), Ok(2)]);
+        assert_eq!(source.next(), Some(Ok(1)));
+        assert_eq!(source.next(), Some(Err("boom")));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_indexed() {
+        let output: Vec<_> = result_samples()
+            .with_filtered_indexed(|i| i.filter(|(_, n)| *n > 2))
+            .collect();
+
+        assert_eq!(
+            output,
+            [
+                (3, Err("boom")),
+                (4, Err("hi")),
+                (5, Ok(3)),
+                (7, Err("zoop")),
+                (8, Ok(5))
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_inspect_residual() {
+        use core::cell::Cell;
+
+        let seen = Cell::new(0);
+        let output: Vec<_> = result_samples()
+            .with_filtered(|i| i.inspect_residual(|_| seen.set(seen.get() + 1)))
+            .collect();
+
+        assert_eq!(seen.get(), 3);
+        assert_eq!(output, result_samples().collect::<Vec<_>>());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_filter_residual() {
+        let output: Vec<_> = result_samples()
+            .with_filtered(|i| i.filter_residual(|err| *err != Err("hi")))
+            .collect();
+
+        assert_eq!(
+            output,
+            [
+                Ok(1),
+                Ok(2),
+                Ok(1),
+                Err("boom"),
+                Ok(3),
+                Ok(1),
+                Err("zoop"),
+                Ok(5)
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_keeps_trailing_divergents() {
+        let output: Vec<_> = result_samples()
+            .chain([Err("end")])
+            .with_filtered(|i| i.filter(|n| *n > 2))
+            .collect();
+
+        assert_eq!(
+            output,
+            [
+                Err("boom"),
+                Err("hi"),
+                Ok(3),
+                Err("zoop"),
+                Ok(5),
+                Err("end")
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_reserves_from_size_hint() {
+        let buf = buffer::Buffer::default();
+        let _output = (0..100)
+            .map(Ok::<_, ()>)
+            .with_filtered_buf(buf.clone(), |i| i);
+
+        assert!(buf.borrow().capacity() >= 25);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn folded_all() {
+        use core::cell::Cell;
+
+        let total = Cell::new(0);
+        let output = result_samples().with_folding_all(|i| total.set(i.sum::<u32>()));
+
+        assert_eq!(output, Err(vec![Err("boom"), Err("hi"), Err("zoop")]));
+        assert_eq!(total.get(), 13);
+        assert_eq!(
+            result_samples()
+                .take(3)
+                .with_folding_all(|i| i.sum::<u32>()),
+            Ok(4)
+        );
+    }
+
+    #[test]
+    fn folded() {
+        result_samples()
+            .with_folding(|i| i.sum::<u32>())
+            .expect_err("foldable sum of sample data should error");
+        assert_eq!(
+            result_samples()
+                .take(3)
+                .with_folding(|i| i.sum::<u32>())
+                .unwrap(),
+            4
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn folded_observes_break() {
+        let mut log = Vec::new();
+        let output = result_samples().with_folding(|mut i| {
+            let mut total = 0;
+            while let Some(n) = i.next() {
+                assert!(!i.broke());
+                total += n;
+            }
+            assert!(i.broke());
+            log.push(format!("stopped at {} with {:?}", total, i.residual()));
+            total
+        });
+
+        assert_eq!(output, Err("boom"));
+        assert_eq!(log, ["stopped at 4 with Some(Err(\"boom\"))"]);
+    }
+
+    #[test]
+    fn folded_infallible() {
+        let source = || (1..=4).map(Ok::<u32, core::convert::Infallible>);
+        assert_eq!(source().with_folding_infallible(|i| i.sum::<u32>()), Ok(10));
+        assert_eq!(
+            source().with_folding_infallible(|i| i.fold(0, |acc, n| acc * 10 + n)),
+            Ok(1234)
+        );
+    }
+
+    #[test]
+    fn folded_counted() {
+        assert_eq!(
+            result_samples().with_folding_counted(|i| i.sum::<u32>()),
+            (Err("boom"), 4)
+        );
+        assert_eq!(
+            result_samples()
+                .take(3)
+                .with_folding_counted(|i| i.sum::<u32>()),
+            (Ok(4), 3)
+        );
+        assert_eq!(
+            result_samples().with_folding_counted(|i| i.take(2).sum::<u32>()),
+            (Ok(3), 2)
+        );
+    }
+
+    #[test]
+    fn folded_try() {
+        fn product_to_4(mut i: impl Iterator<Item = u32>) -> ControlFlow<u32, u32> {
+            i.try_fold(1, |acc, n| match acc * n {
+                product if product > 4 => ControlFlow::Break(product),
+                product => ControlFlow::Continue(product),
+            })
+        }
+
+        assert_eq!(
+            result_samples()
+                .take(3)
+                .with_folding_try(|i| product_to_4(i)),
+            TryFolded::Completed(2)
+        );
+        assert_eq!(
+            result_samples().with_folding_try(|i| product_to_4(i)),
+            TryFolded::Diverged(Err("boom"))
+        );
+        assert_eq!(
+            [Ok(1), Ok(2), Ok(3), Err("boom")]
+                .into_iter()
+                .with_folding_try(|i| product_to_4(i)),
+            TryFolded::Stopped(6)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn folded_collect() {
+        let collected: Result<Vec<u32>, &str> = result_samples().take(3).with_collect();
+        assert_eq!(collected, Ok(vec![1, 2, 1]));
+
+        let collected: Result<Vec<u32>, &str> = result_samples().with_collect();
+        assert_eq!(collected, Err("boom"));
+
+        let collected: Option<String> = [Some('h'), Some('i')].into_iter().with_collect();
+        assert_eq!(collected.as_deref(), Some("hi"));
+    }
+
+    #[test]
+    fn folded_ref() {
+        let mut source = result_samples().peekable();
+
+        assert_eq!(source.with_folding_ref(|i| i.sum::<u32>()), Ok(4));
+        assert_eq!(source.next(), Some(Err("boom")));
+        // the next fold starts at "hi", so it has nothing to sum.
+        assert_eq!(source.with_folding_ref(|i| i.sum::<u32>()), Ok(0));
+        assert_eq!(source.next(), Some(Err("hi")));
+        assert_eq!(source.with_folding_ref(|i| i.sum::<u32>()), Ok(4));
+    }
+
+    #[test]
+    fn folded_options() {
+        assert_eq!(option_samples().with_folding(|i| i.sum::<u32>()), None);
+        assert_eq!(
+            option_samples().take(3).with_folding(|i| i.sum::<u32>()),
+            Some(4)
+        );
+    }
+
+    // anyhow::Error isn't Clone, so this checks that nothing needs to clone
+    // the divergent values themselves.
+    #[cfg(feature = "std")]
+    fn anyhow_samples() -> impl Iterator<Item = anyhow::Result<u32>> {
+        result_samples().map(|item| item.map_err(anyhow::Error::msg))
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn filtered_anyhow() {
+        let output: Vec<String> = anyhow_samples()
+            .with_filtered(|i| i.filter(|n| *n > 2))
+            .map(|item| match item {
+                Ok(n) => n.to_string(),
+                Err(err) => err.to_string(),
+            })
+            .collect();
+
+        assert_eq!(output, ["boom", "hi", "3", "zoop", "5"]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn folded_anyhow() {
+        let err = anyhow_samples()
+            .with_folding(|i| i.sum::<u32>())
+            .expect_err("foldable sum of sample data should error");
+        assert_eq!(err.to_string(), "boom");
+        assert_eq!(
+            anyhow_samples()
+                .take(3)
+                .with_folding(|i| i.sum::<u32>())
+                .unwrap(),
+            4
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn folded_fnonce() {
+        let offsets = vec![10, 20];
+        let output = result_samples().take(3).with_folding(move |i| {
+            // consuming `offsets` makes this closure FnOnce
+            let mut offsets = offsets.into_iter();
+            i.map(|n| n + offsets.next().unwrap_or(0)).sum::<u32>()
+        });
+
+        assert_eq!(output, Ok(34));
+    }
+
+    #[test]
+    fn folded_by_ref() {
+        let mut source = result_samples();
+
+        assert_eq!(
+            source.with_folding_by_ref(|i| i.take(3).sum::<u32>()),
+            Ok(4)
+        );
+        assert_eq!(source.next(), Some(Err("boom")));
+        assert_eq!(source.with_folding_by_ref(|i| i.sum::<u32>()), Err("hi"));
+        assert_eq!(source.next(), Some(Ok(3)));
+    }
+
+    #[test]
+    fn folded_scan() {
+        let capped_sum = |acc: &mut u32, n| {
+            *acc += n;
+            if *acc > 3 {
+                ControlFlow::Break(Err("too big"))
+            } else {
+                ControlFlow::Continue(())
+            }
+        };
+
+        let output: Result<u32, &str> = result_samples().take(2).with_folding_scan(0, capped_sum);
+        assert_eq!(output, Ok(3));
+        let output: Result<u32, &str> = result_samples().with_folding_scan(0, capped_sum);
+        assert_eq!(output, Err("too big"));
+        let output: Result<u32, &str> = result_samples().skip(2).with_folding_scan(0, capped_sum);
+        assert_eq!(output, Err("boom"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn folded_scan_other_accumulator() {
+        // the accumulator doesn't have to be the same type as the values.
+        let runs = |acc: &mut Vec<(u32, usize)>, n| {
+            match acc.last_mut() {
+                Some((last, count)) if *last == n => *count += 1,
+                _ => acc.push((n, 1)),
+            }
+            if acc.len() > 3 {
+                ControlFlow::Break(Err("too many runs"))
+            } else {
+                ControlFlow::Continue(())
+            }
+        };
+        let output: Result<Vec<(u32, usize)>, &str> = [Ok(1), Ok(1), Ok(2), Ok(1)]
+            .into_iter()
+            .with_folding_scan(Vec::new(), runs);
+        assert_eq!(output, Ok(vec![(1, 2), (2, 1), (1, 1)]));
+        let output: Result<Vec<(u32, usize)>, &str> = [Ok(1), Ok(2), Ok(1), Ok(2)]
+            .into_iter()
+            .with_folding_scan(Vec::new(), runs);
+        assert_eq!(output, Err("too many runs"));
+        let count = |acc: &mut usize, _| {
+            *acc += 1;
+            ControlFlow::Continue(())
+        };
+        let output: Option<usize> = [Some(2), Some(3)].into_iter().with_folding_scan(0, count);
+        assert_eq!(output, Some(2));
+        let output: Option<usize> = [Some(2), None].into_iter().with_folding_scan(0, count);
+        assert_eq!(output, None);
+    }
+
+    #[test]
+    fn folded_aggregates() {
+        assert_eq!(result_samples().with_sum(), Err("boom"));
+        assert_eq!(result_samples().take(3).with_sum(), Ok(4));
+        assert_eq!(result_samples().with_product(), Err("boom"));
+        assert_eq!(result_samples().take(3).with_product(), Ok(2));
+
+        let max: Result<Option<u32>, &str> = result_samples().with_max();
+        assert_eq!(max, Err("boom"));
+        let max: Result<Option<u32>, &str> = result_samples().take(3).with_max();
+        assert_eq!(max, Ok(Some(2)));
+        let max: Result<Option<u32>, &str> = result_samples().take(0).with_max();
+        assert_eq!(max, Ok(None));
+        let min: Result<Option<u32>, &str> = result_samples().with_min();
+        assert_eq!(min, Err("boom"));
+        let min: Result<Option<u32>, &str> = result_samples().take(3).with_min();
+        assert_eq!(min, Ok(Some(1)));
+        let min: Result<Option<u32>, &str> = result_samples().take(0).with_min();
+        assert_eq!(min, Ok(None));
+    }
+
+    #[test]
+    fn folded_aggregates_stop_at_divergent() {
+        // nothing past the first divergent value is pulled.
+        let source = || {
+            [Ok(3), Err("boom"), Ok(1)]
+                .into_iter()
+                .chain(core::iter::from_fn(|| panic!("pulled too far")))
+        };
+        let max: Result<Option<u32>, &str> = source().with_max();
+        assert_eq!(max, Err("boom"));
+        let min: Result<Option<u32>, &str> = source().with_min();
+        assert_eq!(min, Err("boom"));
+
+        // the divergent value can go into any type it converts into.
+        let max: Option<Option<u32>> = [Some(3), None, Some(5)].into_iter().with_max();
+        assert_eq!(max, None);
+        let min: Option<Option<u32>> = [Some(3), Some(2)].into_iter().with_min();
+        assert_eq!(min, Some(Some(2)));
+    }
+
+    #[test]
+    fn folded_count_and_last() {
+        let count: Result<usize, &str> = result_samples().take(3).with_count();
+        assert_eq!(count, Ok(3));
+        let count: Result<usize, &str> = result_samples().with_count();
+        assert_eq!(count, Err("boom"));
+
+        let last: Result<Option<u32>, &str> = result_samples().take(3).with_last();
+        assert_eq!(last, Ok(Some(1)));
+        let last: Result<Option<u32>, &str> = result_samples().with_last();
+        assert_eq!(last, Err("boom"));
+        let last: Result<Option<u32>, &str> = result_samples().take(0).with_last();
+        assert_eq!(last, Ok(None));
+
+        // nothing past the first divergent value is pulled.
+        let last: Result<Option<u32>, &str> = [Ok(3), Err("boom")]
+            .into_iter()
+            .chain(core::iter::from_fn(|| panic!("pulled too far")))
+            .with_last();
+        assert_eq!(last, Err("boom"));
+        let last: Option<Option<u32>> = [Some(3), None].into_iter().with_last();
+        assert_eq!(last, None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn folded_try_for_each() {
+        let mut seen = Vec::new();
+        let output: Result<(), &str> = result_samples().with_try_for_each(|n| seen.push(n));
+
+        assert_eq!(output, Err("boom"));
+        assert_eq!(seen, [1, 2, 1]);
+
+        seen.clear();
+        let output: Result<(), &str> = result_samples().take(3).with_try_for_each(|n| seen.push(n));
+        assert_eq!(output, Ok(()));
+        assert_eq!(seen, [1, 2, 1]);
+    }
+
+    #[test]
+    fn folded_find() {
+        // found before the first error
+        let found: Result<_, &str> = result_samples().with_find(|n| *n == 2);
+        assert_eq!(found, Ok(Some(2)));
+        // only matches after an error, so the error wins
+        let found: Result<_, &str> = result_samples().with_find(|n| *n == 3);
+        assert_eq!(found, Err("boom"));
+        let found: Result<_, &str> = result_samples().take(3).with_find(|n| *n == 3);
+        assert_eq!(found, Ok(None));
+    }
+
+    #[test]
+    fn folded_find_map() {
+        let doubled = |n: u32| if n > 1 { Some(n * 2) } else { None };
+
+        let found: Result<_, &str> = result_samples().with_find_map(doubled);
+        assert_eq!(found, Ok(Some(4)));
+        let found: Result<_, &str> = result_samples().skip(2).with_find_map(doubled);
+        assert_eq!(found, Err("boom"));
+        let found: Result<Option<u32>, &str> = result_samples().take(1).with_find_map(doubled);
+        assert_eq!(found, Ok(None));
+    }
+
+    #[test]
+    fn folded_remainder() {
+        let (output, mut rest) =
+            result_samples().with_folding_remainder(|i| i.take(2).sum::<u32>());
+        assert_eq!(output, Ok(3));
+        assert_eq!(rest.next(), Some(Ok(1)));
+        assert_eq!(rest.next(), Some(Err("boom")));
+
+        let (output, mut rest) = result_samples().with_folding_remainder(|i| i.sum::<u32>());
+        assert_eq!(output, Err("boom"));
+        assert_eq!(rest.next(), Some(Err("hi")));
+    }
 }
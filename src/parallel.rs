@@ -0,0 +1,101 @@
+/*
+ Copyright 2022 The Iteritor Authors
+
+ Licensed under the Apache License, Version 2.0 (the "License");
+ you may not use this file except in compliance with the License.
+ You may obtain a copy of the License at
+
+      https://www.apache.org/licenses/LICENSE-2.0
+
+ Unless required by applicable law or agreed to in writing, software
+ distributed under the License is distributed on an "AS IS" BASIS,
+ WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ See the License for the specific language governing permissions and
+ limitations under the License.
+ */
+
+//! Running the filtering pipeline over a rayon [`IndexedParallelIterator`].
+
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+
+use rayon::iter::{Either, IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+use crate::try_polyfill::Try;
+
+/// Including this trait adds the `par_with_filtered` helper function to any
+/// indexed rayon parallel iterator.
+pub trait ParIterFiltered<T>: IndexedParallelIterator<Item = T> {
+    /// A parallel version of [`crate::IterFiltered::with_filtered_indexed`]:
+    /// `f` is given a parallel iterator of the 'normal' values paired with
+    /// their positions in the source, and the results of the chain it returns
+    /// are recombined with the divergent values into the same order
+    /// `with_filtered` would give.
+    ///
+    /// Unlike the sequential version, this isn't lazy. The source is split
+    /// into normal and divergent values up front, and a divergent value is
+    /// put before the first output whose index is greater than its own, so
+    /// `f` needs to carry the indices through its chain for the order to be
+    /// meaningful.
+    fn par_with_filtered<O, U, F>(self, f: F) -> Vec<T>
+    where
+        Self: Sized,
+        F: FnOnce(rayon::vec::IntoIter<(usize, U)>) -> O,
+        O: ParallelIterator<Item = (usize, U)>,
+        T: Try<Output = U> + Send,
+        U: Send,
+    {
+        let (normal, divergent): (Vec<_>, Vec<_>) =
+            self.enumerate()
+                .partition_map(|(index, item)| match item.branch() {
+                    ControlFlow::Continue(output) => Either::Left((index, output)),
+                    ControlFlow::Break(residual) => {
+                        Either::Right((index, T::from_residual(residual)))
+                    }
+                });
+
+        // collect keeps the chain's order, so this only needs a merge.
+        let output: Vec<_> = f(normal.into_par_iter()).collect();
+        let mut divergent = divergent.into_iter().peekable();
+        let mut merged = Vec::with_capacity(output.len() + divergent.len());
+        for (index, output) in output {
+            while let Some((_, item)) = divergent.next_if(|(before, _)| *before < index) {
+                merged.push(item);
+            }
+            merged.push(T::from_output(output));
+        }
+        merged.extend(divergent.map(|(_, item)| item));
+        merged
+    }
+}
+
+impl<I, T> ParIterFiltered<T> for I where I: IndexedParallelIterator<Item = T> {}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{filtered::tests::result_samples, IterFiltered};
+
+    #[test]
+    fn matches_sequential() {
+        let samples: Vec<_> = result_samples().collect();
+
+        assert_eq!(
+            samples
+                .clone()
+                .into_par_iter()
+                .par_with_filtered(|i| i.filter(|(_, n)| *n > 2)),
+            result_samples()
+                .with_filtered(|i| i.filter(|n| *n > 2))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            samples
+                .into_par_iter()
+                .par_with_filtered(|i| i.map(|(index, n)| (index, n * 2))),
+            result_samples()
+                .with_filtered(|i| i.map(|n| n * 2))
+                .collect::<Vec<_>>()
+        );
+    }
+}
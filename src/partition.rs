@@ -0,0 +1,114 @@
+/*
+ Copyright 2022 The Iteritor Authors
+
+ Licensed under the Apache License, Version 2.0 (the "License");
+ you may not use this file except in compliance with the License.
+ You may obtain a copy of the License at
+
+      https://www.apache.org/licenses/LICENSE-2.0
+
+ Unless required by applicable law or agreed to in writing, software
+ distributed under the License is distributed on an "AS IS" BASIS,
+ WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ See the License for the specific language governing permissions and
+ limitations under the License.
+ */
+
+//! Splitting a fallible iterator into separate normal and divergent streams,
+//! used by [`crate::IterFiltered::partition_filtered`].
+
+use core::fmt;
+
+use crate::{filtered::FilteredIter, ControlFlowBuffer};
+
+/// The two halves of a partitioned iterator, sharing one buffer.
+///
+/// The halves aren't independent: only pulling from `normal` advances the
+/// source, buffering any divergent values it passes on the way, and `divergent`
+/// just drains what has been buffered so far. So `divergent` running out only
+/// means `normal` hasn't uncovered any more yet, and the buffer grows by every
+/// divergent value `normal` is pulled past until `divergent` catches up.
+pub struct Partitioned<Input, Buffer> {
+    pub normal: FilteredIter<Input, Buffer>,
+    pub divergent: DivergentIter<Buffer>,
+}
+
+impl<I, B> Partitioned<I, B>
+where
+    I: Iterator,
+    B: ControlFlowBuffer,
+{
+    pub fn new(input_iter: I, buffer: B) -> Self {
+        Self {
+            normal: FilteredIter::new(input_iter, buffer.clone()),
+            divergent: DivergentIter::new(buffer),
+        }
+    }
+}
+
+/// Iterator over the divergent values buffered by the `normal` side of a
+/// [`Partitioned`].
+///
+/// This isn't fused: it returns `None` whenever the buffer is empty, but may
+/// have more to give once the `normal` side has been advanced further.
+pub struct DivergentIter<Buffer> {
+    buffer: Buffer,
+}
+
+impl<B: ControlFlowBuffer> DivergentIter<B> {
+    pub fn new(buffer: B) -> Self {
+        Self { buffer }
+    }
+}
+
+impl<B: ControlFlowBuffer> fmt::Debug for DivergentIter<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DivergentIter")
+            .field("buffered", &self.buffer.len())
+            .finish()
+    }
+}
+
+impl<B: ControlFlowBuffer> Iterator for DivergentIter<B> {
+    type Item = B::Item;
+    fn next(&mut self) -> Option<B::Item> {
+        self.buffer.pop()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // more may turn up later, so there's no upper bound.
+        (self.buffer.len(), None)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{buffer::Buffer, filtered::tests::result_samples, IterFiltered};
+
+    #[test]
+    fn normal_then_divergent() {
+        let mut parts = result_samples().partition_filtered(Buffer::default());
+
+        assert_eq!(parts.divergent.next(), None);
+        assert_eq!(
+            parts.normal.by_ref().collect::<Vec<_>>(),
+            [1, 2, 1, 3, 1, 5]
+        );
+        assert_eq!(parts.divergent.size_hint(), (3, None));
+        assert_eq!(
+            parts.divergent.collect::<Vec<_>>(),
+            [Err("boom"), Err("hi"), Err("zoop")]
+        );
+    }
+
+    #[test]
+    fn interleaved() {
+        let mut parts = result_samples().partition_filtered(Buffer::default());
+
+        assert_eq!(parts.normal.nth(2), Some(1));
+        assert_eq!(parts.divergent.next(), None);
+        assert_eq!(parts.normal.next(), Some(3));
+        assert_eq!(parts.divergent.next(), Some(Err("boom")));
+        assert_eq!(parts.divergent.next(), Some(Err("hi")));
+        assert_eq!(parts.divergent.next(), None);
+    }
+}
@@ -0,0 +1,374 @@
+/*
+ Copyright 2022 The Iteritor Authors
+
+ Licensed under the Apache License, Version 2.0 (the "License");
+ you may not use this file except in compliance with the License.
+ You may obtain a copy of the License at
+
+      https://www.apache.org/licenses/LICENSE-2.0
+
+ Unless required by applicable law or agreed to in writing, software
+ distributed under the License is distributed on an "AS IS" BASIS,
+ WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ See the License for the specific language governing permissions and
+ limitations under the License.
+ */
+
+//! Ways of turning the residual of a divergent value back into an item to
+//! be buffered by a [`crate::filtered::FilteredIter`].
+
+use core::{convert::Infallible, marker::PhantomData, ops::ControlFlow};
+
+use crate::tagged::Item;
+use crate::try_polyfill::{FromResidual, Try};
+
+/// Converts the residual of a divergent source item `S` into the value that
+/// gets buffered for recombination, or `None` to drop it entirely.
+pub trait ResidualMap<S: Try> {
+    type Output;
+
+    fn map_residual(&mut self, residual: S::Residual) -> Option<Self::Output>;
+
+    /// Splits a source item into its output, or what (if anything) gets
+    /// buffered in its place. By default this branches it and hands the
+    /// residual to [`ResidualMap::map_residual`].
+    #[inline]
+    fn split(&mut self, item: S) -> ControlFlow<Option<Self::Output>, S::Output> {
+        match item.branch() {
+            ControlFlow::Continue(output) => ControlFlow::Continue(output),
+            ControlFlow::Break(residual) => ControlFlow::Break(self.map_residual(residual)),
+        }
+    }
+}
+
+/// [`Try`] types that can tell whether they're divergent without being
+/// taken apart, so a divergent value can be kept whole rather than split
+/// into its residual and rebuilt. See [`IntactMap`].
+pub trait BranchIntact: Try + Sized {
+    /// Like [`Try::branch`], but a divergent value is handed back as it is.
+    fn branch_intact(self) -> ControlFlow<Self, Self::Output>;
+}
+
+impl<T> BranchIntact for Option<T> {
+    fn branch_intact(self) -> ControlFlow<Self, T> {
+        match self {
+            Some(output) => ControlFlow::Continue(output),
+            None => ControlFlow::Break(None),
+        }
+    }
+}
+
+impl<T, E> BranchIntact for Result<T, E> {
+    fn branch_intact(self) -> ControlFlow<Self, T> {
+        match self {
+            Ok(output) => ControlFlow::Continue(output),
+            err @ Err(_) => ControlFlow::Break(err),
+        }
+    }
+}
+
+impl<B, C> BranchIntact for ControlFlow<B, C> {
+    fn branch_intact(self) -> ControlFlow<Self, C> {
+        match self {
+            ControlFlow::Continue(output) => ControlFlow::Continue(output),
+            brk @ ControlFlow::Break(_) => ControlFlow::Break(brk),
+        }
+    }
+}
+
+/// The default [`ResidualMap`], which rebuilds the original divergent value
+/// with [`crate::try_polyfill::FromResidual`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FromResidualMap;
+
+impl<S: Try> ResidualMap<S> for FromResidualMap {
+    type Output = S;
+
+    fn map_residual(&mut self, residual: S::Residual) -> Option<S> {
+        Some(S::from_residual(residual))
+    }
+}
+
+/// Residual types that can't be constructed at all, so that a [`Try`] type
+/// with one (such as `Result<T, Infallible>`) never diverges. See
+/// [`crate::IterFiltered::with_infallible`].
+pub trait Uninhabited {
+    fn unreachable(self) -> !;
+}
+
+impl Uninhabited for Infallible {
+    fn unreachable(self) -> ! {
+        match self {}
+    }
+}
+
+impl<E: Uninhabited> Uninhabited for Result<Infallible, E> {
+    fn unreachable(self) -> ! {
+        match self {
+            Ok(never) => match never {},
+            Err(err) => err.unreachable(),
+        }
+    }
+}
+
+impl<B: Uninhabited> Uninhabited for ControlFlow<B, Infallible> {
+    fn unreachable(self) -> ! {
+        match self {
+            ControlFlow::Continue(never) => match never {},
+            ControlFlow::Break(brk) => brk.unreachable(),
+        }
+    }
+}
+
+/// Residual types that hold nothing but the divergent value's payload, such
+/// as the error of a `Result`, so it can be taken out on its own. See
+/// [`crate::IterFiltered::drain_into`].
+pub trait ResidualError {
+    type Error;
+    fn into_error(self) -> Self::Error;
+}
+
+impl<E> ResidualError for Result<Infallible, E> {
+    type Error = E;
+    fn into_error(self) -> E {
+        match self {
+            Ok(never) => match never {},
+            Err(err) => err,
+        }
+    }
+}
+
+impl ResidualError for Option<Infallible> {
+    type Error = ();
+    fn into_error(self) {}
+}
+
+impl<B> ResidualError for ControlFlow<B, Infallible> {
+    type Error = B;
+    fn into_error(self) -> B {
+        match self {
+            ControlFlow::Continue(never) => match never {},
+            ControlFlow::Break(brk) => brk,
+        }
+    }
+}
+
+/// A [`ResidualMap`] that buffers each divergent value just as it came,
+/// using [`BranchIntact`] rather than rebuilding it from its residual.
+/// See [`crate::filtered::FilteredIter::buffer_intact`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IntactMap;
+
+impl<S: BranchIntact> ResidualMap<S> for IntactMap {
+    type Output = S;
+
+    fn map_residual(&mut self, residual: S::Residual) -> Option<S> {
+        Some(S::from_residual(residual))
+    }
+    #[inline]
+    fn split(&mut self, item: S) -> ControlFlow<Option<S>, S::Output> {
+        match item.branch_intact() {
+            ControlFlow::Continue(output) => ControlFlow::Continue(output),
+            ControlFlow::Break(item) => ControlFlow::Break(Some(item)),
+        }
+    }
+}
+
+/// A [`ResidualMap`] that rebuilds each divergent value as some other type
+/// `R` with [`FromResidual`], the same conversion the `?` operator does. For
+/// `Result`s that's `From` on the error type.
+/// See [`crate::IterFiltered::with_filtered_into`].
+pub struct ConvertResidual<R>(PhantomData<fn() -> R>);
+
+impl<R> ConvertResidual<R> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<R> Default for ConvertResidual<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R> Clone for ConvertResidual<R> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<S, R> ResidualMap<S> for ConvertResidual<R>
+where
+    S: Try,
+    R: FromResidual<S::Residual>,
+{
+    type Output = R;
+
+    fn map_residual(&mut self, residual: S::Residual) -> Option<R> {
+        Some(R::from_residual(residual))
+    }
+}
+
+/// A [`ResidualMap`] that buffers each residual as an
+/// [`Item::Divergent`]. See [`crate::IterFiltered::with_filtered_tagged`].
+pub struct TagResidual<U>(PhantomData<fn() -> U>);
+
+impl<U> TagResidual<U> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<U> Default for TagResidual<U> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<U> Clone for TagResidual<U> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Try, U> ResidualMap<S> for TagResidual<U> {
+    type Output = Item<U, S::Residual>;
+
+    fn map_residual(&mut self, residual: S::Residual) -> Option<Self::Output> {
+        Some(Item::Divergent(residual))
+    }
+}
+
+/// A [`ResidualMap`] that hands each residual straight to a function and
+/// buffers nothing. See [`crate::IterFiltered::with_filtered_sink`].
+#[derive(Clone)]
+pub struct SinkResidual<F>(F);
+
+impl<F> SinkResidual<F> {
+    pub fn new(sink: F) -> Self {
+        Self(sink)
+    }
+}
+
+impl<S, F> ResidualMap<S> for SinkResidual<F>
+where
+    S: Try,
+    F: FnMut(S::Residual),
+{
+    type Output = Infallible;
+
+    fn map_residual(&mut self, residual: S::Residual) -> Option<Infallible> {
+        (self.0)(residual);
+        None
+    }
+}
+
+/// A [`ResidualMap`] that passes the output of another through a function.
+/// See [`crate::filtered::FilteredIter::map_residual`].
+pub struct MapResidual<M, F, Y> {
+    inner: M,
+    f: F,
+    output: PhantomData<fn() -> Y>,
+}
+
+impl<M, F, Y> MapResidual<M, F, Y> {
+    pub fn new(inner: M, f: F) -> Self {
+        Self {
+            inner,
+            f,
+            output: PhantomData,
+        }
+    }
+}
+
+impl<M: Clone, F: Clone, Y> Clone for MapResidual<M, F, Y> {
+    fn clone(&self) -> Self {
+        Self::new(self.inner.clone(), self.f.clone())
+    }
+}
+
+impl<S, M, F, Y> ResidualMap<S> for MapResidual<M, F, Y>
+where
+    S: Try,
+    M: ResidualMap<S>,
+    F: FnMut(M::Output) -> Y,
+{
+    type Output = Y;
+
+    fn map_residual(&mut self, residual: S::Residual) -> Option<Y> {
+        self.inner.map_residual(residual).map(&mut self.f)
+    }
+    fn split(&mut self, item: S) -> ControlFlow<Option<Y>, S::Output> {
+        match self.inner.split(item) {
+            ControlFlow::Continue(output) => ControlFlow::Continue(output),
+            ControlFlow::Break(item) => ControlFlow::Break(item.map(&mut self.f)),
+        }
+    }
+}
+
+/// A [`ResidualMap`] that calls a function with a reference to each
+/// residual before handing it on unchanged.
+/// See [`crate::filtered::FilteredIter::inspect_residual`].
+#[derive(Clone)]
+pub struct InspectResidual<M, F> {
+    inner: M,
+    f: F,
+}
+
+impl<M, F> InspectResidual<M, F> {
+    pub fn new(inner: M, f: F) -> Self {
+        Self { inner, f }
+    }
+}
+
+impl<S, M, F> ResidualMap<S> for InspectResidual<M, F>
+where
+    S: Try,
+    M: ResidualMap<S>,
+    F: FnMut(&S::Residual),
+{
+    type Output = M::Output;
+
+    fn map_residual(&mut self, residual: S::Residual) -> Option<M::Output> {
+        (self.f)(&residual);
+        self.inner.map_residual(residual)
+    }
+}
+
+/// A [`ResidualMap`] that only keeps the values its predicate accepts.
+/// See [`crate::filtered::FilteredIter::filter_residual`].
+#[derive(Clone)]
+pub struct FilterResidual<M, P> {
+    inner: M,
+    predicate: P,
+}
+
+impl<M, P> FilterResidual<M, P> {
+    pub fn new(inner: M, predicate: P) -> Self {
+        Self { inner, predicate }
+    }
+}
+
+impl<S, M, P> ResidualMap<S> for FilterResidual<M, P>
+where
+    S: Try,
+    M: ResidualMap<S>,
+    P: FnMut(&M::Output) -> bool,
+{
+    type Output = M::Output;
+
+    fn map_residual(&mut self, residual: S::Residual) -> Option<M::Output> {
+        self.inner
+            .map_residual(residual)
+            .filter(|item| (self.predicate)(item))
+    }
+    fn split(&mut self, item: S) -> ControlFlow<Option<M::Output>, S::Output> {
+        match self.inner.split(item) {
+            ControlFlow::Continue(output) => ControlFlow::Continue(output),
+            ControlFlow::Break(item) => {
+                ControlFlow::Break(item.filter(|item| (self.predicate)(item)))
+            }
+        }
+    }
+}
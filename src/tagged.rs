@@ -0,0 +1,62 @@
+/*
+ Copyright 2022 The Iteritor Authors
+
+ Licensed under the Apache License, Version 2.0 (the "License");
+ you may not use this file except in compliance with the License.
+ You may obtain a copy of the License at
+
+      https://www.apache.org/licenses/LICENSE-2.0
+
+ Unless required by applicable law or agreed to in writing, software
+ distributed under the License is distributed on an "AS IS" BASIS,
+ WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ See the License for the specific language governing permissions and
+ limitations under the License.
+ */
+
+//! The output of [`crate::IterFiltered::with_filtered_tagged`], which keeps
+//! the chain's output and the source's divergent values apart in the type.
+
+use core::ops::ControlFlow;
+
+use crate::try_polyfill::{FromResidual, Try};
+
+/// Either a value the chain yielded or a divergent value passed through
+/// from the source, as its residual. Unlike recombining into the source's
+/// own [`Try`] type, a chain that yields `Err`s itself can't be mistaken
+/// for one that passed an error through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Item<U, R> {
+    Value(U),
+    Divergent(R),
+}
+
+/// The residual of an [`Item`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaggedResidual<R>(pub R);
+
+#[cfg(feature = "nightly")]
+impl<U, R> core::ops::Residual<U> for TaggedResidual<R> {
+    type TryType = Item<U, R>;
+}
+
+impl<U, R> Try for Item<U, R> {
+    type Output = U;
+    type Residual = TaggedResidual<R>;
+
+    fn from_output(output: U) -> Self {
+        Item::Value(output)
+    }
+    fn branch(self) -> ControlFlow<Self::Residual, U> {
+        match self {
+            Item::Value(output) => ControlFlow::Continue(output),
+            Item::Divergent(residual) => ControlFlow::Break(TaggedResidual(residual)),
+        }
+    }
+}
+
+impl<U, R> FromResidual<TaggedResidual<R>> for Item<U, R> {
+    fn from_residual(TaggedResidual(residual): TaggedResidual<R>) -> Self {
+        Item::Divergent(residual)
+    }
+}
@@ -17,17 +17,23 @@
 //! Polyfill for the rust Try (and related) trait that is currently unstable.
 //! See https://doc.rust-lang.org/std/ops/trait.Try.html for docs.
 //! Any divergences in behaviour should be considered bugs.
-
-pub use is_nightly::*;
+//!
+//! Outputs and residuals can borrow, as long as what they borrow from is
+//! named by a lifetime parameter of the type itself, as in `Option<&'a T>`
+//! or `Result<&'a str, E>`. What they can't do is borrow from the value
+//! being branched, since [`Try::branch`] takes it by value (the real trait
+//! is no different). Filtering a borrowing source works like any other, as
+//! long as what it borrows outlives the pipeline.
 
 #[cfg(feature = "nightly")]
-mod is_nightly {
-    pub use core::ops::FromResidual;
-    pub use core::ops::Try;
-}
-
+pub use core::ops::{FromResidual, Try};
 #[cfg(not(feature = "nightly"))]
-mod is_nightly {
+pub use polyfill::*;
+
+// This is also built for tests on nightly, so that it can be checked
+// against the real thing.
+#[cfg(any(not(feature = "nightly"), test))]
+mod polyfill {
     use core::{convert::Infallible, ops::ControlFlow};
 
     pub trait FromResidual<R = <Self as Try>::Residual> {
@@ -72,6 +78,24 @@ mod is_nightly {
         }
     }
 
+    /// `Continue` is the output and `Break` is divergent, with the residual
+    /// being a `ControlFlow<B, Infallible>` holding the break value, as in
+    /// core.
+    impl<B, C> Try for ControlFlow<B, C> {
+        type Output = C;
+        type Residual = ControlFlow<B, Infallible>;
+
+        fn from_output(output: Self::Output) -> Self {
+            ControlFlow::Continue(output)
+        }
+        fn branch(self) -> ControlFlow<Self::Residual, Self::Output> {
+            match self {
+                ControlFlow::Continue(output) => ControlFlow::Continue(output),
+                ControlFlow::Break(b) => ControlFlow::Break(ControlFlow::Break(b)),
+            }
+        }
+    }
+
     impl<T> FromResidual for Option<T> {
         fn from_residual(_residual: Option<Infallible>) -> Self {
             None
@@ -88,4 +112,68 @@ mod is_nightly {
             Err(err)
         }
     }
+
+    impl<B, C> FromResidual for ControlFlow<B, C> {
+        fn from_residual(residual: ControlFlow<B, Infallible>) -> Self {
+            match residual {
+                ControlFlow::Break(b) => ControlFlow::Break(b),
+                ControlFlow::Continue(never) => match never {},
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod tests {
+    use super::polyfill;
+    use core::{convert::Infallible, fmt::Debug, ops::ControlFlow};
+
+    fn branch_agrees<T>(value: T)
+    where
+        T: Clone + core::ops::Try + polyfill::Try,
+        <T as core::ops::Try>::Output: Debug + PartialEq<<T as polyfill::Try>::Output>,
+        <T as core::ops::Try>::Residual: Debug + PartialEq<<T as polyfill::Try>::Residual>,
+    {
+        match (
+            core::ops::Try::branch(value.clone()),
+            polyfill::Try::branch(value),
+        ) {
+            (ControlFlow::Continue(core), ControlFlow::Continue(poly)) => assert!(core == poly),
+            (ControlFlow::Break(core), ControlFlow::Break(poly)) => assert!(core == poly),
+            (core, _) => panic!("polyfill branched differently from core ({:?})", core),
+        }
+    }
+
+    #[test]
+    fn branch_matches_core() {
+        for sample in crate::filtered::tests::result_samples() {
+            branch_agrees(sample);
+        }
+        for sample in crate::filtered::tests::option_samples() {
+            branch_agrees(sample);
+        }
+        branch_agrees(ControlFlow::<&str, u32>::Continue(1));
+        branch_agrees(ControlFlow::<&str, u32>::Break("stop"));
+    }
+
+    #[test]
+    fn from_output_and_residual_match_core() {
+        let core: Result<u32, &str> = core::ops::Try::from_output(1);
+        let poly: Result<u32, &str> = polyfill::Try::from_output(1);
+        assert_eq!(core, poly);
+
+        let residual: Result<Infallible, &str> = Err("boom");
+        let core: Result<u32, Option<&str>> = core::ops::FromResidual::from_residual(residual);
+        let poly: Result<u32, Option<&str>> = polyfill::FromResidual::from_residual(residual);
+        assert_eq!(core, poly);
+
+        let core: Option<u32> = core::ops::FromResidual::from_residual(None);
+        let poly: Option<u32> = polyfill::FromResidual::from_residual(None);
+        assert_eq!(core, poly);
+
+        let residual = ControlFlow::<&str, Infallible>::Break("stop");
+        let core: ControlFlow<&str, u32> = core::ops::FromResidual::from_residual(residual);
+        let poly: ControlFlow<&str, u32> = polyfill::FromResidual::from_residual(residual);
+        assert_eq!(core, poly);
+    }
 }